@@ -0,0 +1,260 @@
+//! Expectimax player, and the adversary spawning tiles against it.
+//!
+//! Player nodes take the best of the four moves, chance nodes average over
+//! every cell and value a tile may spawn with, weighted by the game's
+//! `SpawnRule`: a 2 (90%) or a 4 (10%) under the classic one. Leaves are
+//! scored by a `Heuristic`.
+
+use std::sync::Arc;
+
+use game::{Direction, Grid, GameManager, Tile};
+use spawn::SpawnRule;
+use transposition::TranspositionTable;
+
+/// Static evaluation of a board, bigger is better.
+pub trait Heuristic {
+    fn evaluate(&self, grid: &Grid) -> f64;
+}
+
+/// Weighted sum of the classic 2048 features, all computed on the log2 of
+/// tile values.
+#[derive(Debug, Clone, Copy)]
+pub struct WeightedHeuristic {
+    /// Rows and columns should keep increasing or decreasing.
+    pub monotonicity: f64,
+    /// Neighbour tiles should have close values.
+    pub smoothness: f64,
+    /// Weight of `ln(number of empty cells)`.
+    pub empty: f64,
+    /// Bonus when the biggest tile sits in a corner.
+    pub max_in_corner: f64
+}
+
+impl Default for WeightedHeuristic {
+    fn default() -> WeightedHeuristic {
+        WeightedHeuristic { monotonicity: 1.0, smoothness: 0.1, empty: 2.7, max_in_corner: 1.0 }
+    }
+}
+
+fn rank(grid: &Grid, (x, y): (usize, usize)) -> f64 {
+    match grid.cells[x][y] {
+        Some(ref tile) => (tile.value as f64).log2(),
+        None => 0.0,
+    }
+}
+
+// Penalty for every line going up then down (or down then up).
+fn monotonicity(grid: &Grid) -> f64 {
+    let line_penalty = |cells: &[(usize, usize)]| {
+        let (mut inc, mut dec) = (0.0, 0.0);
+        for pair in cells.windows(2) {
+            let (a, b) = (rank(grid, pair[0]), rank(grid, pair[1]));
+            if a > b { dec += a - b } else { inc += b - a }
+        }
+        f64::min(inc, dec)
+    };
+    let mut penalty = 0.0;
+    for y in 0..grid.height {
+        let row = (0..grid.width).map(|x| (x, y)).collect::<Vec<_>>();
+        penalty += line_penalty(&row);
+    }
+    for x in 0..grid.width {
+        let col = (0..grid.height).map(|y| (x, y)).collect::<Vec<_>>();
+        penalty += line_penalty(&col);
+    }
+    -penalty
+}
+
+fn smoothness(grid: &Grid) -> f64 {
+    let mut diff = 0.0;
+    grid.each_cell(|x, y, tile| {
+        if tile.is_none() {
+            return;
+        }
+        for &(nx, ny) in [(x + 1, y), (x, y + 1)].iter() {
+            if grid.cell_content((nx, ny)).is_some() {
+                diff += (rank(grid, (x, y)) - rank(grid, (nx, ny))).abs();
+            }
+        }
+    });
+    -diff
+}
+
+fn max_in_corner(grid: &Grid) -> f64 {
+    let (right, bottom) = (grid.width - 1, grid.height - 1);
+    let max = grid.max_tile();
+    let corners = [(0, 0), (0, bottom), (right, 0), (right, bottom)];
+    if max > 0 && corners.iter().any(|&pos| grid.cells[pos.0][pos.1].map(|t| t.value) == Some(max)) {
+        (max as f64).log2()
+    } else {
+        0.0
+    }
+}
+
+impl Heuristic for WeightedHeuristic {
+    fn evaluate(&self, grid: &Grid) -> f64 {
+        let empty = grid.available_cells().len();
+        self.monotonicity * monotonicity(grid) +
+            self.smoothness * smoothness(grid) +
+            self.empty * if empty > 0 { (empty as f64).ln() } else { 0.0 } +
+            self.max_in_corner * max_in_corner(grid)
+    }
+}
+
+/// Expectimax searcher.
+#[derive(Debug, Clone)]
+pub struct Expectimax<H> {
+    /// Number of player moves looked ahead.
+    pub depth: usize,
+    /// Chance branches less likely than this are evaluated right away.
+    pub prob_threshold: f64,
+    pub heuristic: H,
+    /// Chance nodes already valued, by this searcher or others sharing the
+    /// table under the same spawn rule. A hit may come from a branch cut at
+    /// another probability, so moves can differ from an uncached search.
+    pub cache: Option<Arc<TranspositionTable>>
+}
+
+impl Default for Expectimax<WeightedHeuristic> {
+    fn default() -> Expectimax<WeightedHeuristic> {
+        Expectimax { depth: 2, prob_threshold: 0.0001, heuristic: WeightedHeuristic::default(), cache: None }
+    }
+}
+
+impl<H: Heuristic> Expectimax<H> {
+    /// Best move for the current position, `None` if the game is over.
+    pub fn best_move(&self, gm: &GameManager) -> Option<Direction> {
+        self.best_move_on(&gm.grid, &gm.spawn_rule)
+    }
+
+    /// Best move on `grid`, tiles spawning by `rule`. `None` if no move is
+    /// left.
+    pub fn best_move_on(&self, grid: &Grid, rule: &SpawnRule) -> Option<Direction> {
+        best_evaluated(&self.evaluate_moves(grid, rule))
+    }
+
+    /// Expected value of each of the four moves on `grid`, tiles spawning by
+    /// `rule`, in `Direction::all_directions()` order, `None` for those
+    /// moving nothing.
+    pub fn evaluate_moves(&self, grid: &Grid, rule: &SpawnRule) -> Vec<(Direction, Option<f64>)> {
+        Direction::all_directions().into_iter().map(|dir| {
+            (dir, grid.slide(dir).map(|(after, _)| self.chance_node(&after, rule, self.depth, 1.0)))
+        }).collect()
+    }
+
+    fn max_node(&self, grid: &Grid, rule: &SpawnRule, depth: usize, prob: f64) -> f64 {
+        let mut best = None;
+        for &dir in Direction::all_directions().iter() {
+            if let Some((after, _)) = grid.slide(dir) {
+                let value = self.chance_node(&after, rule, depth, prob);
+                if !best.is_some_and(|v| value <= v) {
+                    best = Some(value);
+                }
+            }
+        }
+        // no move left: worse than anything still playable
+        best.unwrap_or(-1e9)
+    }
+
+    fn chance_node(&self, grid: &Grid, rule: &SpawnRule, depth: usize, prob: f64) -> f64 {
+        if depth == 0 || prob < self.prob_threshold || !grid.cells_available() {
+            return self.heuristic.evaluate(grid);
+        }
+        if let Some(value) = self.cache.as_ref().and_then(|c| c.get(grid.zobrist(), depth as u32)) {
+            return value;
+        }
+        let mut total = 0.0;
+        for (tile, p) in grid.spawn_outcomes(rule) {
+            let mut next = grid.clone();
+            next.insert_tile(tile);
+            total += p * self.max_node(&next, rule, depth - 1, prob * p);
+        }
+        if let Some(ref cache) = self.cache {
+            cache.insert(grid.zobrist(), depth as u32, total);
+        }
+        total
+    }
+}
+
+/// First of the best valued moves in `scores`, as `evaluate_moves()` gives
+/// them. `None` if no move has a value.
+pub fn best_evaluated(scores: &[(Direction, Option<f64>)]) -> Option<Direction> {
+    scores.iter().fold(None, |best: Option<(Direction, f64)>, &(dir, value)| match (best, value) {
+        (Some((_, b)), Some(v)) if b >= v => best,
+        (_, Some(v)) => Some((dir, v)),
+        (_, None) => best,
+    }).map(|(dir, _)| dir)
+}
+
+/// Picks spawns that leave the player worst off: minimax where the
+/// opponent chooses the cell and value of every new tile.
+#[derive(Debug, Clone)]
+pub struct Adversary<H> {
+    /// Number of player moves looked ahead after the spawn.
+    pub depth: usize,
+    pub heuristic: H
+}
+
+impl Default for Adversary<WeightedHeuristic> {
+    fn default() -> Adversary<WeightedHeuristic> {
+        Adversary { depth: 1, heuristic: WeightedHeuristic::default() }
+    }
+}
+
+impl<H: Heuristic> Adversary<H> {
+    /// Worst tile among `values` to drop on `grid`, `None` if it is full.
+    pub fn worst_spawn(&self, grid: &Grid, values: &[isize]) -> Option<Tile> {
+        let mut worst = None;
+        for pos in grid.available_cells() {
+            for &value in values {
+                let tile = Tile::new(pos, value);
+                let mut next = grid.clone();
+                next.insert_tile(tile);
+                let score = self.player_node(&next, self.depth, values);
+                if !worst.is_some_and(|(_, w)| score >= w) {
+                    worst = Some((tile, score));
+                }
+            }
+        }
+        worst.map(|(tile, _)| tile)
+    }
+
+    fn player_node(&self, grid: &Grid, depth: usize, values: &[isize]) -> f64 {
+        if depth == 0 {
+            return self.heuristic.evaluate(grid);
+        }
+        let mut best = None;
+        for &dir in Direction::all_directions().iter() {
+            if let Some((after, _)) = grid.slide(dir) {
+                let score = self.spawn_node(&after, depth - 1, values);
+                if !best.is_some_and(|v| score <= v) {
+                    best = Some(score);
+                }
+            }
+        }
+        best.unwrap_or(-1e9)
+    }
+
+    fn spawn_node(&self, grid: &Grid, depth: usize, values: &[isize]) -> f64 {
+        if depth == 0 || !grid.cells_available() {
+            return self.heuristic.evaluate(grid);
+        }
+        let mut worst = None;
+        for pos in grid.available_cells() {
+            for &value in values {
+                let mut next = grid.clone();
+                next.insert_tile(Tile::new(pos, value));
+                let score = self.player_node(&next, depth, values);
+                if !worst.is_some_and(|v| score >= v) {
+                    worst = Some(score);
+                }
+            }
+        }
+        worst.unwrap()
+    }
+}
+
+/// Best move according to the default `Expectimax` searcher.
+pub fn best_move(gm: &GameManager) -> Option<Direction> {
+    Expectimax::default().best_move(gm)
+}
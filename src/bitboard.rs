@@ -0,0 +1,199 @@
+//! Compact 4x4 board for fast search.
+//!
+//! Each cell is a 4 bit exponent (`0` empty, `n` for the tile `2^n`), row
+//! `y` takes the 16 bits starting at `16 * y` and cell `(x, y)` the nibble
+//! at `4 * x` inside it. Moves are looked up per row (or column) in tables
+//! built once for all 65536 possible lines.
+
+use game::{Direction, Grid, Tile};
+
+const ROW_MASK: u64 = 0xFFFF;
+const COL_MASK: u64 = 0x000F_000F_000F_000F;
+
+struct MoveTables {
+    // xor to apply to a row / column to slide it
+    row_left: Vec<u16>,
+    row_right: Vec<u16>,
+    col_up: Vec<u64>,
+    col_down: Vec<u64>,
+    // points scored by sliding a line towards index 0 / index 3
+    score_left: Vec<u32>,
+    score_right: Vec<u32>
+}
+
+lazy_static! {
+    static ref TABLES: MoveTables = MoveTables::new();
+}
+
+fn reverse_row(row: u16) -> u16 {
+    (row >> 12) | ((row >> 4) & 0x00F0) | ((row << 4) & 0x0F00) | (row << 12)
+}
+
+// spread the 4 nibbles of a line in column 0
+fn unpack_col(row: u16) -> u64 {
+    let row = row as u64;
+    (row | (row << 12) | (row << 24) | (row << 36)) & COL_MASK
+}
+
+// Slides a line towards nibble 0, like `GameManager::move_to` does: a tile
+// merges at most once per move, 2^15 tiles never merge.
+fn slide_line(row: u16) -> (u16, u32) {
+    let mut line = [0u8; 4];
+    for (i, cell) in line.iter_mut().enumerate() {
+        *cell = ((row >> (4 * i)) & 0xF) as u8;
+    }
+    let mut out = [0u8; 4];
+    let mut score = 0;
+    let mut n = 0;
+    let mut can_merge = false;
+    for &cell in line.iter().filter(|&&c| c != 0) {
+        if can_merge && out[n - 1] == cell && cell < 15 {
+            out[n - 1] += 1;
+            score += 1 << out[n - 1];
+            can_merge = false;
+        } else {
+            out[n] = cell;
+            n += 1;
+            can_merge = true;
+        }
+    }
+    let mut result = 0;
+    for (i, &cell) in out.iter().enumerate() {
+        result |= (cell as u16) << (4 * i);
+    }
+    (result, score)
+}
+
+impl MoveTables {
+    fn new() -> MoveTables {
+        let mut t = MoveTables {
+            row_left: vec![0; 65536], row_right: vec![0; 65536],
+            col_up: vec![0; 65536], col_down: vec![0; 65536],
+            score_left: vec![0; 65536], score_right: vec![0; 65536]
+        };
+        for row in 0..65536usize {
+            let r = row as u16;
+            let (left, score) = slide_line(r);
+            let rev = reverse_row(r);
+            let right = reverse_row(slide_line(rev).0);
+
+            t.row_left[row] = r ^ left;
+            t.row_right[row] = r ^ right;
+            t.col_up[row] = unpack_col(r) ^ unpack_col(left);
+            t.col_down[row] = unpack_col(r) ^ unpack_col(right);
+            t.score_left[row] = score;
+            t.score_right[row] = slide_line(rev).1;
+        }
+        t
+    }
+}
+
+/// A 4x4 board packed in a `u64`.
+#[derive(PartialEq, Eq, Hash, Debug, Clone, Copy, Default)]
+pub struct BitBoard(pub u64);
+
+impl BitBoard {
+    /// Packs a 4x4 grid. `None` if the grid has another size or holds a
+    /// tile that is not a power of two up to 2^15.
+    pub fn from_grid(grid: &Grid) -> Option<BitBoard> {
+        if grid.width != 4 || grid.height != 4 {
+            return None;
+        }
+        let mut board = BitBoard(0);
+        for x in 0..4 {
+            for y in 0..4 {
+                if let Some(ref tile) = grid.cells[x][y] {
+                    let v = tile.value;
+                    if !(2..=1 << 15).contains(&v) || v & (v - 1) != 0 {
+                        return None;
+                    }
+                    board.set((x, y), (v as f64).log2() as u8);
+                }
+            }
+        }
+        Some(board)
+    }
+
+    /// Unpacks the board, tiles come without any move history.
+    pub fn to_grid(&self) -> Grid {
+        let mut grid = Grid::new(4);
+        for x in 0..4 {
+            for y in 0..4 {
+                let e = self.get((x, y));
+                if e != 0 {
+                    grid.insert_tile(Tile::new((x, y), 1 << e));
+                }
+            }
+        }
+        grid
+    }
+
+    /// Exponent at `(x, y)`, 0 for an empty cell.
+    pub fn get(&self, (x, y): (usize, usize)) -> u8 {
+        ((self.0 >> (16 * y + 4 * x)) & 0xF) as u8
+    }
+
+    pub fn set(&mut self, (x, y): (usize, usize), exponent: u8) {
+        let shift = 16 * y + 4 * x;
+        self.0 = (self.0 & !(0xF << shift)) | ((exponent as u64 & 0xF) << shift);
+    }
+
+    fn row(&self, y: usize) -> u16 {
+        ((self.0 >> (16 * y)) & ROW_MASK) as u16
+    }
+
+    fn col(&self, x: usize) -> u16 {
+        let c = (self.0 >> (4 * x)) & COL_MASK;
+        (c | (c >> 12) | (c >> 24) | (c >> 36)) as u16
+    }
+
+    /// Board after sliding towards `dir`, without spawning a tile, and the
+    /// points scored. `None` if nothing moves.
+    pub fn slide(&self, dir: Direction) -> Option<(BitBoard, u32)> {
+        let t = &*TABLES;
+        let mut board = self.0;
+        let mut score = 0;
+        match dir {
+            Direction::Left | Direction::Right => {
+                for y in 0..4 {
+                    let row = self.row(y) as usize;
+                    let (diff, s) = if dir == Direction::Left {
+                        (t.row_left[row], t.score_left[row])
+                    } else {
+                        (t.row_right[row], t.score_right[row])
+                    };
+                    board ^= (diff as u64) << (16 * y);
+                    score += s;
+                }
+            }
+            Direction::Up | Direction::Down => {
+                for x in 0..4 {
+                    let col = self.col(x) as usize;
+                    let (diff, s) = if dir == Direction::Up {
+                        (t.col_up[col], t.score_left[col])
+                    } else {
+                        (t.col_down[col], t.score_right[col])
+                    };
+                    board ^= diff << (4 * x);
+                    score += s;
+                }
+            }
+        }
+        if board == self.0 { None } else { Some((BitBoard(board), score)) }
+    }
+
+    /// Number of empty cells.
+    pub fn count_empty(&self) -> usize {
+        (0..16).filter(|i| (self.0 >> (4 * i)) & 0xF == 0).count()
+    }
+
+    /// Biggest exponent on the board.
+    pub fn max_exponent(&self) -> u8 {
+        (0..16).map(|i| ((self.0 >> (4 * i)) & 0xF) as u8).max().unwrap_or(0)
+    }
+
+    /// Whether any move is possible.
+    pub fn moves_available(&self) -> bool {
+        Direction::all_directions().into_iter().any(|dir| self.slide(dir).is_some())
+    }
+}
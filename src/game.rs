@@ -1,9 +1,20 @@
+//! Board model and game rules.
+
 use std::fmt;
 use std::iter;
-use rand;
+use rand::{Rng, SeedableRng};
 
+use rng::GameRng;
+use spawn::SpawnRule;
 
-#[derive(PartialEq, Debug, Clone, Copy)]
+/// Cells on the shortest side a board may have.
+pub const MIN_SIZE: usize = 2;
+/// Cells on the longest side a board may have, the most the front ends
+/// can draw.
+pub const MAX_SIZE: usize = 8;
+
+/// A move the player can make; every tile slides towards this side.
+#[derive(PartialEq, Debug, Clone, Copy, Serialize, Deserialize)]
 pub enum Direction {
     Up,
     Right,
@@ -12,7 +23,8 @@ pub enum Direction {
 }
 
 impl Direction {
-    fn to_vector(self) -> (isize, isize) {
+    /// Unit step `(dx, dy)` of this direction, `x` being the column.
+    pub fn to_vector(self) -> (isize, isize) {
         match self {
             Direction::Up    => (0, -1),
             Direction::Right => (1, 0),
@@ -22,25 +34,122 @@ impl Direction {
     }
 
     // Haskell succ/pred???
-    fn all_directions<'r>() -> Vec<Direction> {
+    /// All four directions, clockwise starting from `Up`.
+    pub fn all_directions<'r>() -> Vec<Direction> {
         vec![Direction::Up, Direction::Right, Direction::Down, Direction::Left]
     }
+
+    fn from_vector(v: (isize, isize)) -> Direction {
+        match v {
+            (0, -1) => Direction::Up,
+            (1, 0) => Direction::Right,
+            (0, 1) => Direction::Down,
+            (-1, 0) => Direction::Left,
+            _ => unreachable!("not a unit step: {:?}", v),
+        }
+    }
+}
+
+/// One of the 8 ways to turn or mirror a board, rotations being clockwise
+/// as seen on screen. Quarter turns and transposes swap width and height.
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Hash)]
+pub enum Symmetry {
+    Identity,
+    Rotate90,
+    Rotate180,
+    Rotate270,
+    /// Left and right swapped.
+    FlipHorizontal,
+    /// Top and bottom swapped.
+    FlipVertical,
+    /// Mirrored along the top left to bottom right diagonal.
+    Transpose,
+    /// Mirrored along the other diagonal.
+    AntiTranspose,
+}
+
+impl Symmetry {
+    pub fn all() -> Vec<Symmetry> {
+        vec![Symmetry::Identity, Symmetry::Rotate90, Symmetry::Rotate180, Symmetry::Rotate270,
+             Symmetry::FlipHorizontal, Symmetry::FlipVertical, Symmetry::Transpose, Symmetry::AntiTranspose]
+    }
+
+    /// Those mapping a `width` x `height` board onto itself, all 8 on a
+    /// square board, 4 on others.
+    pub fn preserving(width: usize, height: usize) -> Vec<Symmetry> {
+        Symmetry::all().into_iter().filter(|s| width == height || !s.swaps_axes()).collect()
+    }
+
+    fn swaps_axes(self) -> bool {
+        match self {
+            Symmetry::Rotate90 | Symmetry::Rotate270 | Symmetry::Transpose | Symmetry::AntiTranspose => true,
+            _ => false,
+        }
+    }
+
+    /// The symmetry undoing this one.
+    pub fn inverse(self) -> Symmetry {
+        match self {
+            Symmetry::Rotate90 => Symmetry::Rotate270,
+            Symmetry::Rotate270 => Symmetry::Rotate90,
+            other => other,
+        }
+    }
+
+    /// Size of the image of a `width` x `height` board.
+    pub fn dimensions(self, width: usize, height: usize) -> (usize, usize) {
+        if self.swaps_axes() { (height, width) } else { (width, height) }
+    }
+
+    /// Where cell `(x, y)` of a `width` x `height` board goes.
+    pub fn position(self, (x, y): (usize, usize), width: usize, height: usize) -> (usize, usize) {
+        let (w, h) = (width - 1, height - 1);
+        match self {
+            Symmetry::Identity => (x, y),
+            Symmetry::Rotate90 => (h - y, x),
+            Symmetry::Rotate180 => (w - x, h - y),
+            Symmetry::Rotate270 => (y, w - x),
+            Symmetry::FlipHorizontal => (w - x, y),
+            Symmetry::FlipVertical => (x, h - y),
+            Symmetry::Transpose => (y, x),
+            Symmetry::AntiTranspose => (h - y, w - x),
+        }
+    }
+
+    /// The move on the transformed board matching `dir` on the original:
+    /// sliding then transforming gives the same board as transforming then
+    /// sliding the returned way.
+    pub fn direction(self, dir: Direction) -> Direction {
+        let (dx, dy) = dir.to_vector();
+        Direction::from_vector(match self {
+            Symmetry::Identity => (dx, dy),
+            Symmetry::Rotate90 => (-dy, dx),
+            Symmetry::Rotate180 => (-dx, -dy),
+            Symmetry::Rotate270 => (dy, -dx),
+            Symmetry::FlipHorizontal => (-dx, dy),
+            Symmetry::FlipVertical => (dx, -dy),
+            Symmetry::Transpose => (dy, dx),
+            Symmetry::AntiTranspose => (-dy, -dx),
+        })
+    }
 }
 
+/// Visit order of the cells for a move, farthest cells first so tiles never
+/// jump over each other.
 pub struct Traversal {
     xs: Vec<usize>,
     ys: Vec<usize>,
 
     idx: usize,
     max_idx: usize,
-    size: usize
+    height: usize
 }
 
 impl Traversal {
-    pub fn new(size: usize, dir: Direction) -> Traversal {
+    pub fn new(width: usize, height: usize, dir: Direction) -> Traversal {
         let (x, y) = dir.to_vector();
-        let mut xs = (0..size).collect::<Vec<usize>>();
-        let mut ys = (0..size).collect::<Vec<usize>>();
+        let mut xs = (0..width).collect::<Vec<usize>>();
+        let mut ys = (0..height).collect::<Vec<usize>>();
         if x == 1 {
             xs.reverse()
         }
@@ -48,8 +157,8 @@ impl Traversal {
             ys.reverse()
         }
         Traversal { xs: xs.to_vec(), ys: ys.to_vec(),
-                    idx: 0, max_idx: size * size,
-                    size: size,
+                    idx: 0, max_idx: width * height,
+                    height: height,
         }
     }
 }
@@ -60,15 +169,19 @@ impl Iterator for Traversal {
         if self.idx == self.max_idx {
             None
         } else {
-            let ret = (self.xs[self.idx / self.size],
-                       self.ys[self.idx % self.size]);
+            let ret = (self.xs[self.idx / self.height],
+                       self.ys[self.idx % self.height]);
             self.idx += 1;
             Some(ret)
         }
     }
 }
 
-#[derive(PartialEq, Clone, Debug, Copy)]
+/// A numbered tile.
+///
+/// `prev_pos` and `merged_from` describe what happened to the tile during the
+/// last move and are reset by `GameManager::prepare_tiles`.
+#[derive(PartialEq, Clone, Debug, Copy, Serialize, Deserialize)]
 pub struct Tile {
     pub x: usize,
     pub y: usize,
@@ -101,7 +214,7 @@ impl Tile {
 
 impl fmt::Display for Tile {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "[{:4b}]", self.value)
+        write!(f, "[{:4}]", self.value)
     }
 }
 
@@ -120,37 +233,115 @@ impl fmt::Display for Tile {
 //
 
 
-#[derive(PartialEq, Debug, Clone)]
+// Zobrist keys, one random number per cell and tile exponent; the hash of
+// a board is the xor of the keys of its tiles. Bigger boards and tiles wrap
+// around, which only costs collisions.
+const ZOBRIST_SIDE: usize = 16;
+const ZOBRIST_EXPONENTS: usize = 32;
+
+lazy_static! {
+    static ref ZOBRIST: Vec<u64> = {
+        let mut rng = GameRng::from_seed(0x2048);
+        (0..ZOBRIST_SIDE * ZOBRIST_SIDE * ZOBRIST_EXPONENTS).map(|_| rng.next_u64()).collect()
+    };
+}
+
+fn zobrist_key((x, y): (usize, usize), value: isize) -> u64 {
+    let exponent = (value as u64).trailing_zeros() as usize % ZOBRIST_EXPONENTS;
+    ZOBRIST[((x % ZOBRIST_SIDE) * ZOBRIST_SIDE + y % ZOBRIST_SIDE) * ZOBRIST_EXPONENTS + exponent]
+}
+
+/// A `width` x `height` board, indexed as `cells[x][y]`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(from = "GridFields")]
 pub struct Grid {
-    pub size: usize,
-    pub cells: Vec<Vec<Option<Tile>>>
+    pub width: usize,
+    pub height: usize,
+    /// Writing cells directly leaves `zobrist()` stale until `rehash()`.
+    pub cells: Vec<Vec<Option<Tile>>>,
+    #[serde(skip)]
+    hash: u64
+}
+
+// A grid as serialized, the hash is computed again on reading.
+#[derive(Deserialize)]
+struct GridFields {
+    width: usize,
+    height: usize,
+    cells: Vec<Vec<Option<Tile>>>
+}
+
+impl From<GridFields> for Grid {
+    fn from(fields: GridFields) -> Grid {
+        let mut grid = Grid { width: fields.width, height: fields.height, cells: fields.cells, hash: 0 };
+        grid.rehash();
+        grid
+    }
+}
+
+// The hash follows from the cells, even a stale one.
+impl PartialEq for Grid {
+    fn eq(&self, other: &Grid) -> bool {
+        self.width == other.width && self.height == other.height && self.cells == other.cells
+    }
 }
 
 impl Grid {
+    /// Creates an empty `size` x `size` board.
     pub fn new(size: usize) -> Grid {
+        Grid::with_dimensions(size, size)
+    }
+
+    /// Creates an empty board `width` cells wide and `height` cells high.
+    pub fn with_dimensions(width: usize, height: usize) -> Grid {
         let mut cells = Vec::new();
-        for _ in 0..size {
+        for _ in 0..width {
             let mut row = Vec::new();
-            row.extend(iter::repeat(None).take(size));
+            row.extend(iter::repeat(None).take(height));
             cells.push(row);
         }
         Grid {
-            size: size,
-            cells: cells
+            width: width,
+            height: height,
+            cells: cells,
+            hash: 0
+        }
+    }
+
+    /// Zobrist hash of the tile values and positions, kept up to date by
+    /// `insert_tile`, `remove_tile` and `move_tile`. The move history of the
+    /// tiles does not count.
+    pub fn zobrist(&self) -> u64 {
+        self.hash
+    }
+
+    /// Recomputes `zobrist()` from the cells, after writing them directly.
+    pub fn rehash(&mut self) {
+        let mut hash = 0;
+        // whatever their size, a grid just read is not checked yet
+        for (x, col) in self.cells.iter().enumerate() {
+            for (y, cell) in col.iter().enumerate() {
+                if let Some(ref tile) = *cell {
+                    hash ^= zobrist_key((x, y), tile.value);
+                }
+            }
         }
+        self.hash = hash;
     }
 
-    pub fn random_available_cell(&self) -> Option<(usize, usize)> {
+    /// Picks an empty cell using `rng`.
+    pub fn random_available_cell<R: Rng>(&self, rng: &mut R) -> Option<(usize, usize)> {
         let cells = self.available_cells();
 
         match cells.len() {
             0 => None,
             n => {
-                Some(cells[rand::random::<usize>() % n])
+                Some(cells[rng.gen_range(0, n)])
             }
         }
     }
 
+    /// Positions of all empty cells.
     pub fn available_cells(&self) -> Vec<(usize, usize)> {
         let mut cells = Vec::new();
 
@@ -164,8 +355,8 @@ impl Grid {
 
     pub fn each_cell<F>(&self, mut callback: F)
         where F: FnMut(usize, usize, Option<&Tile>) {
-        for x in 0..self.size {
-            for y in 0..self.size {
+        for x in 0..self.width {
+            for y in 0..self.height {
                 callback(x, y, self.cells[x][y].as_ref())
             }
         }
@@ -173,13 +364,24 @@ impl Grid {
 
     pub fn each_mut_cell<F>(&mut self, mut callback: F)
         where F: FnMut(usize, usize, &mut Option<Tile>)  {
-        for x in 0..self.size {
-            for y in 0..self.size {
+        for x in 0..self.width {
+            for y in 0..self.height {
                 callback(x, y, &mut self.cells[x][y])
             }
         }
     }
 
+    /// Value of the biggest tile on the board, 0 if it is empty.
+    pub fn max_tile(&self) -> isize {
+        let mut max = 0;
+        self.each_cell(|_, _, tile| {
+            match tile {
+                Some(t) if t.value > max => max = t.value,
+                _ => ()
+            }
+        });
+        max
+    }
 
     pub fn cells_available(&self) -> bool {
         self.available_cells().len() != 0
@@ -193,19 +395,33 @@ impl Grid {
     //     self.cells[x][y].is_some()
     // }
 
+    /// Puts `tile` at its position, replacing any tile there.
     pub fn insert_tile(&mut self, tile: Tile) {
+        if let Some(ref old) = self.cells[tile.x][tile.y] {
+            self.hash ^= zobrist_key(tile.pos(), old.value);
+        }
+        self.hash ^= zobrist_key(tile.pos(), tile.value);
         self.cells[tile.x][tile.y] = Some(tile.clone());
     }
 
+    /// Empties the cell at the tile's position.
     pub fn remove_tile(&mut self, tile: Tile) {
-        println!("remove ({}, {})", tile.x, tile.y);
-        self.cells[tile.x][tile.y] = None;
+        trace!("remove {:?}", tile.pos());
+        self.clear_cell(tile.pos());
+    }
+
+    fn clear_cell(&mut self, (x, y): (usize, usize)) {
+        if let Some(ref old) = self.cells[x][y] {
+            self.hash ^= zobrist_key((x, y), old.value);
+        }
+        self.cells[x][y] = None;
     }
 
     pub fn within_bounds(&self, (x, y): (usize, usize)) -> bool {
-        x < self.size && y < self.size
+        x < self.width && y < self.height
     }
 
+    /// Tile at the given position, `None` if empty or out of the board.
     pub fn cell_content(&self, (x, y): (usize, usize)) -> Option<Tile> {
         if self.within_bounds((x, y)) {
             self.cells[x][y].clone()
@@ -214,114 +430,79 @@ impl Grid {
         }
     }
 
-    #[allow(dead_code)]
-    pub fn debug_prisize(&self) {
-        for col in self.cells.iter() {
-            for cell in col.iter() {
-                match cell.clone() {
-                    Some(t) => print!("{}\t", t),
-                    None    => print!("[    ]\t"),
-                }
-            }
-            print!("");
-        }
-    }
-
-}
-
-#[derive(Debug)]
-pub struct GameManager {
-    pub size: usize,
-    pub start_tiles: usize,
-
-    pub grid: Grid,
-    pub score: usize,
-    pub playing: bool
-}
-
-impl GameManager {
-    pub fn new(size: usize) -> GameManager {
-        GameManager { size: size,
-                      start_tiles: 2,
-                      grid: Grid::new(size),
-                      score: 0,
-                      playing: false }
-    }
-
-    pub fn setup(&mut self) {
-        self.playing = true;
-
-        self.add_start_tiles();
-    }
-
-    pub fn add_random_tile(&mut self) {
-        if self.grid.cells_available() {
-            let value = if rand::random::<usize>() % 10 < 9 { 2 } else { 4 };
-            let tile = Tile::new(self.grid.random_available_cell().unwrap(), value);
-            print!("add new at {:?}, val = {:?}", tile.pos(), value);
-            self.grid.insert_tile(tile);
-        }
-    }
-
-    fn add_start_tiles(&mut self) {
-        for _ in 0..self.start_tiles {
-            self.add_random_tile();
-        }
+    pub fn move_tile(&mut self, tile: Tile, (x, y): (usize, usize)) {
+        trace!("move {:?} to {:?}", tile.pos(), (x,y));
+        let mut tile = tile;
+        self.clear_cell(tile.pos());
+        tile.update_position((x, y));
+        self.insert_tile(tile);
     }
 
     pub fn prepare_tiles(&mut self) {
-        self.grid.each_mut_cell(|_x, _y, tile| {
+        self.each_mut_cell(|_x, _y, tile| {
             match tile.as_mut() {
-                Some(mut t) => {
+                Some(t) => {
                     t.merged_from = None;
                     t.save_position();
-                    //*tile = Some(t);
                 },
                 None => ()
             }
         })
     }
 
-    pub fn move_tile(&mut self, tile: Tile, (x, y): (usize, usize)) {
-        println!("move {:?} to {:?}", tile.pos(), (x,y));
-        let mut tile = tile;
-        self.grid.cells[tile.x][tile.y] = None;
-        tile.update_position((x, y));
-        //*self.grid.cells.get_mut(tile.x).get_mut(tile.y) = None;
-        self.grid.cells[x][y] = Some(tile);
-        //*pos = Some(tile);
-        //(*pos).unwrap().update_position((x, y));
+    /// The board after sliding towards `dir` and the points scored, without
+    /// spawning any tile. `None` if nothing can move that way.
+    pub fn slide(&self, dir: Direction) -> Option<(Grid, u32)> {
+        let mut grid = self.clone();
+        let outcome = grid.apply_move(dir);
+        if outcome.moved() {
+            Some((grid, outcome.score_delta as u32))
+        } else {
+            None
+        }
+    }
+
+    /// Every tile that may spawn on this board with its probability, the
+    /// values weighted as `rule` says, on a uniformly drawn empty cell. The
+    /// probabilities sum to 1 unless the board is full.
+    pub fn spawn_outcomes(&self, rule: &SpawnRule) -> SpawnOutcomes {
+        let total = rule.values.iter().map(|&(_, w)| w as f64).sum::<f64>();
+        let values = rule.values.iter().filter(|&&(_, w)| w > 0).map(|&(v, w)| (v, w as f64 / total)).collect();
+        SpawnOutcomes { cells: self.available_cells(), values: values, idx: 0 }
     }
 
-    pub fn move_to(&mut self, dir: Direction) -> bool {
-        let mut moved = false;
+    // Slides the tiles in place; spawning, score and win are up to the caller.
+    fn apply_move(&mut self, dir: Direction) -> MoveOutcome {
+        let mut outcome = MoveOutcome::default();
 
         self.prepare_tiles();
         for (x, y) in self.build_traversal(dir) {
-            let tile_opt = self.grid.cell_content((x, y));
+            let tile_opt = self.cell_content((x, y));
             match tile_opt {
                 Some(mut tile) => {
                     let (farthest_pos, next_pos) = self.find_farthest_position((x,y), dir.to_vector());
-                    let next_opt = self.grid.cell_content(next_pos);
+                    let next_opt = self.cell_content(next_pos);
                     match next_opt {
                         Some(ref next) if next.value == tile.value && next.merged_from.is_none() => {
                             let mut merged = Tile::new(next_pos, tile.value * 2);
-                            println!("{:?}, {:?} merged to {:?}", tile.pos(), next_pos, next_pos);
-                            merged.merged_from = Some((tile.pos(), next.pos()));
+                            trace!("{:?}, {:?} merged to {:?}", tile.pos(), next_pos, next_pos);
+                            // where both tiles were before this move
+                            merged.merged_from = Some((tile.pos(), next.prev_pos.unwrap_or(next.pos())));
 
-                            self.grid.insert_tile(merged);
-                            self.grid.remove_tile(tile);
+                            self.insert_tile(merged);
+                            self.remove_tile(tile);
 
                             tile.update_position(next_pos);
 
-                            self.score += merged.value as usize;
-                            // The mighty 2048 tile
-                            moved = true;
+                            outcome.score_delta += merged.value as usize;
+                            outcome.merges.push(TileMerge { sources: merged.merged_from.unwrap(),
+                                                            result: next_pos,
+                                                            value: merged.value });
                         }
                         _ => {
                             if tile.pos() != farthest_pos {
+                                outcome.moves.push(TileMove { from: tile.pos(), to: farthest_pos });
                                 self.move_tile(tile, farthest_pos);
-                                moved = true;
                             }
                         }
                     }
@@ -329,31 +510,20 @@ impl GameManager {
                 _ => ()
             }
         }
-
-        if moved {
-            // xxx moves_av
-            print!("some cell moved, add new one!");
-            self.add_random_tile();
-        }
-
-        moved
-    }
-
-    pub fn moves_available(&self) -> bool {
-        self.grid.cells_available() || self.tile_matches_available()
+        outcome
     }
 
     // visit order
     fn build_traversal(&self, dir: Direction) -> Traversal {
-        Traversal::new(self.size, dir)
+        Traversal::new(self.width, self.height, dir)
     }
 
     fn find_farthest_position(&self, (x, y): (usize, usize), (dx, dy): (isize, isize)) -> ((usize, usize), (usize, usize)) {
         let (mut prev_x, mut prev_y) = (x as isize, y as isize);
         let (mut next_x, mut next_y) = (prev_x + dx, prev_y + dy);
 
-        while self.grid.within_bounds((next_x as usize, next_y as usize)) &&
-              self.grid.cell_available((next_x as usize, next_y as usize)) {
+        while self.within_bounds((next_x as usize, next_y as usize)) &&
+              self.cell_available((next_x as usize, next_y as usize)) {
                   prev_x = next_x;
                   prev_y = next_y;
                   next_x = prev_x + dx;
@@ -364,9 +534,417 @@ impl GameManager {
          (next_x as usize, next_y as usize))
     }
 
+    /// The board turned or mirrored by `sym`, tiles and their last move
+    /// included.
+    pub fn transform(&self, sym: Symmetry) -> Grid {
+        let (width, height) = sym.dimensions(self.width, self.height);
+        let mut grid = Grid::with_dimensions(width, height);
+        let pos = |p| sym.position(p, self.width, self.height);
+        self.each_cell(|x, y, tile| {
+            if let Some(tile) = tile {
+                let mut moved = Tile::new(pos((x, y)), tile.value);
+                moved.prev_pos = tile.prev_pos.map(&pos);
+                moved.merged_from = tile.merged_from.map(|(a, b)| (pos(a), pos(b)));
+                grid.insert_tile(moved);
+            }
+        });
+        grid
+    }
+
+    pub fn rotate90(&self) -> Grid {
+        self.transform(Symmetry::Rotate90)
+    }
+
+    pub fn rotate180(&self) -> Grid {
+        self.transform(Symmetry::Rotate180)
+    }
+
+    pub fn rotate270(&self) -> Grid {
+        self.transform(Symmetry::Rotate270)
+    }
+
+    pub fn flip_horizontal(&self) -> Grid {
+        self.transform(Symmetry::FlipHorizontal)
+    }
+
+    pub fn flip_vertical(&self) -> Grid {
+        self.transform(Symmetry::FlipVertical)
+    }
+
+    pub fn transpose(&self) -> Grid {
+        self.transform(Symmetry::Transpose)
+    }
+
+    // Tile values row by row, 0 for empty cells.
+    fn values(&self) -> Vec<isize> {
+        (0..self.height).flat_map(|y| {
+            (0..self.width).map(move |x| self.cells[x][y].map_or(0, |t| t.value))
+        }).collect()
+    }
+
+    /// The smallest of the board's images by `Symmetry::preserving()`,
+    /// comparing tile values row by row, with the symmetry giving it. Boards
+    /// equal up to a symmetry have the same canonical form, the tiles' last
+    /// move being dropped; a move `dir` on the board is `sym.direction(dir)`
+    /// on its canonical form.
+    pub fn canonical(&self) -> (Grid, Symmetry) {
+        let (mut grid, sym) = Symmetry::preserving(self.width, self.height).into_iter()
+            .map(|sym| (self.transform(sym), sym))
+            .min_by_key(|&(ref grid, _)| grid.values())
+            .unwrap();
+        grid.each_mut_cell(|x, y, tile| {
+            if let Some(ref mut t) = *tile {
+                *t = Tile::new((x, y), t.value);
+            }
+        });
+        (grid, sym)
+    }
+}
+
+/// The tile values row by row, for logs.
+impl fmt::Display for Grid {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for y in 0..self.height {
+            for x in 0..self.width {
+                match self.cells[x][y] {
+                    Some(t) => try!(write!(f, "{}", t)),
+                    None    => try!(write!(f, "[    ]")),
+                }
+            }
+            try!(writeln!(f));
+        }
+        Ok(())
+    }
+}
+
+/// A tile as `(x, y, value)`, how files store them.
+pub type TileSpec = (usize, usize, isize);
+
+/// A tile that slid without merging.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct TileMove {
+    pub from: (usize, usize),
+    pub to: (usize, usize)
+}
+
+/// Two tiles of equal value that became one.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct TileMerge {
+    /// Positions, before the move, of the moving tile and of the tile it
+    /// merged into.
+    pub sources: ((usize, usize), (usize, usize)),
+    pub result: (usize, usize),
+    pub value: isize
+}
+
+/// What a single `GameManager::move_to` did to the board.
+#[derive(PartialEq, Debug, Clone, Default)]
+pub struct MoveOutcome {
+    pub moves: Vec<TileMove>,
+    pub merges: Vec<TileMerge>,
+    /// Tiles dropped after the move, none if nothing moved.
+    pub spawned: Vec<Tile>,
+    pub score_delta: usize,
+    /// The target tile has been reached, in this move or earlier.
+    pub won: bool,
+    /// No move is possible anymore.
+    pub lost: bool
+}
+
+impl MoveOutcome {
+    /// Whether the move changed the board at all.
+    pub fn moved(&self) -> bool {
+        !self.moves.is_empty() || !self.merges.is_empty()
+    }
+}
+
+/// Iterator over the possible spawns of a board, see `Grid::spawn_outcomes`.
+pub struct SpawnOutcomes {
+    cells: Vec<(usize, usize)>,
+    // with their probability
+    values: Vec<(isize, f64)>,
+    idx: usize
+}
+
+impl Iterator for SpawnOutcomes {
+    type Item = (Tile, f64);
+    fn next(&mut self) -> Option<(Tile, f64)> {
+        let n = self.values.len();
+        if self.idx == self.cells.len() * n {
+            None
+        } else {
+            let (value, p) = self.values[self.idx % n];
+            let pos = self.cells[self.idx / n];
+            self.idx += 1;
+            Some((Tile::new(pos, value), p / self.cells.len() as f64))
+        }
+    }
+}
+
+// Everything needed to rewind a move, the rng included so that redoing a
+// move spawns the very same tile.
+#[derive(Debug, Clone)]
+struct Snapshot {
+    grid: Grid,
+    score: usize,
+    moves: usize,
+    won: bool,
+    rng: GameRng,
+    spawn_count: usize
+}
+
+/// Game state and rules: owns the grid, the score and the random source
+/// every spawned tile is drawn from.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GameManager {
+    pub width: usize,
+    pub height: usize,
+    /// How new tiles appear, the classic rule unless changed before
+    /// `setup()`.
+    #[serde(default)]
+    pub spawn_rule: SpawnRule,
+
+    pub grid: Grid,
+    pub score: usize,
+    /// Moves played so far, undone moves excluded.
+    #[serde(default)]
+    pub moves: usize,
+    pub playing: bool,
+
+    /// Tile value that wins the game, 2048 by default.
+    pub target_tile: isize,
+    /// Set once a merge reaches `target_tile` or more.
+    pub won: bool,
+
+    /// Max number of moves kept for `undo()`, oldest are dropped first.
+    pub history_depth: usize,
+    /// Undos left, `None` for unlimited.
+    pub undo_budget: Option<usize>,
+
+    /// Tiles spawned so far, i.e. how far a `SpawnPolicy::Fixed` sequence
+    /// went.
+    #[serde(default)]
+    pub spawn_count: usize,
+
+    keep_playing: bool,
+    rng: GameRng,
+    #[serde(skip)]
+    undo_stack: Vec<Snapshot>,
+    #[serde(skip)]
+    redo_stack: Vec<Snapshot>
+}
+
+impl GameManager {
+    /// New game with a random seed, see `seed()` to replay it.
+    pub fn new(size: usize) -> GameManager {
+        GameManager::with_rng(size, GameRng::new())
+    }
+
+    /// New game whose tile spawns are fully determined by `seed`.
+    pub fn with_seed(size: usize, seed: u64) -> GameManager {
+        GameManager::with_rng(size, GameRng::from_seed(seed))
+    }
+
+    /// New game drawing its tiles from `rng`.
+    pub fn with_rng(size: usize, rng: GameRng) -> GameManager {
+        GameManager::with_dimensions(size, size, rng)
+    }
+
+    /// New game on a `width` x `height` board drawing its tiles from `rng`.
+    pub fn with_dimensions(width: usize, height: usize, rng: GameRng) -> GameManager {
+        GameManager { width: width,
+                      height: height,
+                      spawn_rule: SpawnRule::default(),
+                      grid: Grid::with_dimensions(width, height),
+                      score: 0,
+                      moves: 0,
+                      playing: false,
+                      target_tile: 2048,
+                      won: false,
+                      history_depth: 256,
+                      undo_budget: None,
+                      keep_playing: false,
+                      rng: rng,
+                      spawn_count: 0,
+                      undo_stack: Vec::new(),
+                      redo_stack: Vec::new() }
+    }
+
+    /// Seed of the game's random source.
+    pub fn seed(&self) -> u64 {
+        self.rng.seed()
+    }
+
+    /// Current random source, copying it lets a caller predict spawns.
+    pub fn rng(&self) -> &GameRng {
+        &self.rng
+    }
+
+    /// A copy of the game drawing its spawns from `rng`, without undo
+    /// history, to search ahead without knowing the real spawns.
+    pub fn fork(&self, rng: GameRng) -> GameManager {
+        GameManager { spawn_rule: self.spawn_rule.clone(),
+                      grid: self.grid.clone(),
+                      history_depth: 0,
+                      rng: rng,
+                      undo_stack: Vec::new(),
+                      redo_stack: Vec::new(),
+                      ..*self }
+    }
+
+    /// Starts a game on an empty board by dropping the initial tiles.
+    pub fn setup(&mut self) {
+        self.grid = Grid::with_dimensions(self.width, self.height);
+        self.score = 0;
+        self.moves = 0;
+        self.spawn_count = 0;
+        self.playing = true;
+        self.won = false;
+        self.keep_playing = false;
+        self.undo_stack.clear();
+        self.redo_stack.clear();
+
+        self.add_start_tiles();
+    }
+
+    /// Drops a new tile as the `spawn_rule` says and returns it, `None` if
+    /// the board is full.
+    pub fn add_random_tile(&mut self) -> Option<Tile> {
+        let tile = self.spawn_rule.spawn(&self.grid, &mut self.rng, self.spawn_count);
+        if let Some(tile) = tile {
+            debug!("add new tile at {:?}, value = {}", tile.pos(), tile.value);
+            self.grid.insert_tile(tile);
+            self.spawn_count += 1;
+        }
+        tile
+    }
+
+    fn add_start_tiles(&mut self) {
+        for _ in 0..self.spawn_rule.start_tiles {
+            self.add_random_tile();
+        }
+    }
+
+    pub fn prepare_tiles(&mut self) {
+        self.grid.prepare_tiles()
+    }
+
+    pub fn move_tile(&mut self, tile: Tile, pos: (usize, usize)) {
+        self.grid.move_tile(tile, pos)
+    }
+
+    /// Slides all tiles towards `dir`, merging equal neighbours, and spawns
+    /// a new tile. See `MoveOutcome::moved()` to know if anything changed.
+    pub fn move_to(&mut self, dir: Direction) -> MoveOutcome {
+        // searches and batch games keep no history, spare them the copy
+        let before = if self.history_depth > 0 { Some(self.snapshot()) } else { None };
+        let mut outcome = self.grid.apply_move(dir);
+
+        self.score += outcome.score_delta;
+        // The mighty 2048 tile
+        if outcome.merges.iter().any(|m| m.value >= self.target_tile) {
+            self.won = true;
+        }
+
+        if outcome.moved() {
+            debug!("{:?}: {} moved, {} merged, +{}", dir, outcome.moves.len(),
+                   outcome.merges.len(), outcome.score_delta);
+            for _ in 0..self.spawn_rule.per_move {
+                outcome.spawned.extend(self.add_random_tile());
+            }
+            trace!("board:\n{}", self.grid);
+            self.moves += 1;
+
+            self.redo_stack.clear();
+            self.undo_stack.extend(before);
+            if self.undo_stack.len() > self.history_depth {
+                let extra = self.undo_stack.len() - self.history_depth;
+                self.undo_stack.drain(..extra);
+            }
+        }
+        outcome.won = self.won;
+        outcome.lost = !self.moves_available();
+
+        outcome
+    }
+
+    /// Lets the player go on after reaching the target tile.
+    pub fn keep_playing(&mut self) {
+        self.keep_playing = true;
+    }
+
+    /// Whether the game has stopped: no move is left, or the target tile was
+    /// reached and the player did not choose to keep playing.
+    pub fn is_terminated(&self) -> bool {
+        !self.moves_available() || (self.won && !self.keep_playing)
+    }
+
+    /// Number of moves that can be undone right now, budget included.
+    pub fn undo_available(&self) -> usize {
+        match self.undo_budget {
+            Some(n) => n.min(self.undo_stack.len()),
+            None    => self.undo_stack.len(),
+        }
+    }
+
+    /// Number of undone moves that can be replayed.
+    pub fn redo_available(&self) -> usize {
+        self.redo_stack.len()
+    }
+
+    /// Takes back the last move, restoring the board, score and rng as they
+    /// were before it. Returns `false` if there is nothing to undo or the
+    /// undo budget is spent.
+    pub fn undo(&mut self) -> bool {
+        if self.undo_available() == 0 {
+            return false;
+        }
+        let prev = self.undo_stack.pop().unwrap();
+        let current = self.snapshot();
+        self.redo_stack.push(current);
+        self.restore(prev);
+        if let Some(ref mut n) = self.undo_budget {
+            *n -= 1;
+        }
+        true
+    }
+
+    /// Replays the last undone move. Any new move made after an undo drops
+    /// the redo history.
+    pub fn redo(&mut self) -> bool {
+        match self.redo_stack.pop() {
+            Some(next) => {
+                let current = self.snapshot();
+                self.undo_stack.push(current);
+                self.restore(next);
+                true
+            }
+            None => false
+        }
+    }
+
+    fn snapshot(&self) -> Snapshot {
+        Snapshot { grid: self.grid.clone(), score: self.score, moves: self.moves, won: self.won,
+                   rng: self.rng, spawn_count: self.spawn_count }
+    }
+
+    fn restore(&mut self, snap: Snapshot) {
+        self.grid = snap.grid;
+        self.score = snap.score;
+        self.moves = snap.moves;
+        self.won = snap.won;
+        self.rng = snap.rng;
+        self.spawn_count = snap.spawn_count;
+    }
+
+    /// Whether any move is still possible, i.e. the game is not over.
+    pub fn moves_available(&self) -> bool {
+        self.grid.cells_available() || self.tile_matches_available()
+    }
+
     fn tile_matches_available(&self) -> bool {
-        for x in 0..self.size {
-            for y in 0..self.size {
+        for x in 0..self.width {
+            for y in 0..self.height {
                 match self.grid.cell_content((x,y)) {
                     Some(tile) => {
                         for dir in Direction::all_directions().iter() {
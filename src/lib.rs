@@ -0,0 +1,55 @@
+//! 2048 game engine.
+//!
+//! The engine is UI independent: `GameManager` owns a `Grid` of `Tile`s and
+//! applies `Direction` moves to it. The SDL2 front end shipped with the
+//! `game2048` binary is only built when the `sdl` feature is enabled, so
+//! headless users can depend on this crate with `default-features = false`.
+//!
+//! Diagnostics go through the `log` facade, nothing is printed on stdout.
+//!
+//! ```
+//! use game2048::{GameManager, Direction};
+//!
+//! let mut gm = GameManager::new(4);
+//! gm.setup();
+//! gm.move_to(Direction::Left);
+//! assert!(gm.score % 2 == 0);
+//! ```
+
+#![crate_name = "game2048"]
+
+extern crate rand;
+#[macro_use]
+extern crate log;
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+extern crate serde_json;
+#[macro_use]
+extern crate lazy_static;
+
+pub mod rng;
+pub mod game;
+pub mod save;
+pub mod ai;
+pub mod bitboard;
+pub mod spawn;
+pub mod stats;
+pub mod replay;
+pub mod ntuple;
+pub mod player;
+pub mod rollout;
+pub mod transposition;
+pub mod sim;
+
+pub use rng::GameRng;
+pub use game::{Direction, Symmetry, Tile, Grid, Traversal, GameManager};
+pub use game::{MoveOutcome, TileMove, TileMerge, SpawnOutcomes};
+pub use save::SaveError;
+pub use spawn::{SpawnRule, SpawnPolicy, Difficulty};
+pub use bitboard::BitBoard;
+pub use transposition::{TranspositionTable, CacheStats};
+pub use stats::Stats;
+pub use replay::{Replay, ReplayError, Recorder, Playback};
+pub use ntuple::{NTupleNetwork, NTupleError};
+pub use player::{Player, GameView, GameRunner, HumanPlayer, TrainedPlayer};
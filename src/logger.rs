@@ -0,0 +1,40 @@
+use std::fs::OpenOptions;
+use std::io::{self, Write};
+use std::path::Path;
+use std::sync::Mutex;
+
+use log::{self, Log, Record, Metadata, LevelFilter};
+
+// Minimal `log` sink: one line per record, to stderr or to a file, so
+// diagnostics never mix with what the game prints on stdout.
+struct Logger {
+    out: Mutex<Box<Write + Send>>
+}
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= log::max_level()
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let mut out = self.out.lock().unwrap();
+        let _ = writeln!(out, "{:5} [{}] {}", record.level(), record.target(), record.args());
+    }
+
+    fn flush(&self) {
+        let _ = self.out.lock().unwrap().flush();
+    }
+}
+
+pub fn init(level: LevelFilter, file: Option<&Path>) -> io::Result<()> {
+    let out: Box<Write + Send> = match file {
+        Some(path) => Box::new(try!(OpenOptions::new().create(true).append(true).open(path))),
+        None       => Box::new(io::stderr()),
+    };
+    log::set_max_level(level);
+    log::set_boxed_logger(Box::new(Logger { out: Mutex::new(out) }))
+        .map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))
+}
@@ -1,30 +1,669 @@
-#![crate_name = "game2048"]
-
+extern crate game2048;
+extern crate rand;
+#[macro_use]
+extern crate log;
+#[macro_use]
+extern crate clap;
+#[macro_use]
+extern crate serde_json;
+extern crate num_cpus;
+#[cfg(feature = "sdl")]
 extern crate sdl2;
+#[cfg(feature = "sdl")]
 extern crate sdl2_ttf;
+#[cfg(feature = "sdl")]
 extern crate sdl2_gfx;
-extern crate rand;
+#[cfg(feature = "tui")]
+extern crate termion;
 
+use std::cmp;
 use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
+#[cfg(feature = "sdl")]
 mod ui;
-mod game;
+#[cfg(feature = "tui")]
+mod tui;
+mod logger;
 use std::str::FromStr;
+use clap::{App, Arg, ArgMatches, SubCommand};
+use log::LevelFilter;
+use rand::SeedableRng;
+use game2048::{GameManager, GameRng, Stats, Replay, Recorder, Playback, SpawnRule, SpawnPolicy, Difficulty};
+use game2048::{NTupleNetwork, TranspositionTable, CacheStats, spawn};
+use game2048::ntuple;
+use game2048::game::{MIN_SIZE, MAX_SIZE};
+use game2048::sim::{self, Strategy};
+use game2048::rollout::{Playout, Budget};
+
+#[cfg(feature = "sdl")]
+static DEFAULT_UI: &'static str = "sdl";
+#[cfg(not(feature = "sdl"))]
+static DEFAULT_UI: &'static str = "tui";
+
+fn ui_names() -> Vec<&'static str> {
+    let mut names = vec![];
+    if cfg!(feature = "sdl") {
+        names.push("sdl");
+    }
+    if cfg!(feature = "tui") {
+        names.push("tui");
+    }
+    names
+}
+
+// --data-dir, else $XDG_DATA_HOME/game2048, else ~/.local/share/game2048
+fn data_dir(matches: &ArgMatches) -> PathBuf {
+    if let Some(dir) = matches.value_of_os("data-dir") {
+        return PathBuf::from(dir);
+    }
+    match env::var_os("XDG_DATA_HOME") {
+        Some(ref dir) if !dir.is_empty() => PathBuf::from(dir).join("game2048"),
+        _ => {
+            let home = env::var_os("HOME").map(PathBuf::from).unwrap_or(PathBuf::from("."));
+            home.join(".local").join("share").join("game2048")
+        }
+    }
+}
+
+fn parse_dim(s: &str) -> Result<usize, String> {
+    match usize::from_str(s) {
+        Ok(n) if n >= MIN_SIZE && n <= MAX_SIZE => Ok(n),
+        Ok(n) => Err(format!("{} is out of range, boards have {} to {} cells a side", n, MIN_SIZE, MAX_SIZE)),
+        Err(_) => Err(format!("`{}` is not a number", s)),
+    }
+}
+
+// "4" for a square board, "4x6" for width x height
+fn parse_size(s: &str) -> Result<(usize, usize), String> {
+    let mut dims = s.splitn(2, 'x');
+    let width = try!(parse_dim(dims.next().unwrap_or("")));
+    match dims.next() {
+        Some(h) => Ok((width, try!(parse_dim(h)))),
+        None => Ok((width, width)),
+    }
+}
+
+fn is_number(s: String) -> Result<(), String> {
+    u64::from_str(&s).map(|_| ()).map_err(|_| format!("`{}` is not a number", s))
+}
+
+fn is_positive(s: String) -> Result<(), String> {
+    match usize::from_str(&s) {
+        Ok(n) if n > 0 => Ok(()),
+        _ => Err(format!("`{}` is not a positive number", s)),
+    }
+}
+
+// "2:9,4:1", values with their weights
+fn parse_spawn_values(s: &str) -> Result<Vec<(isize, u32)>, String> {
+    s.split(',').map(|pair| {
+        let mut parts = pair.splitn(2, ':');
+        let value = parts.next().and_then(|v| isize::from_str(v.trim()).ok());
+        let weight = parts.next().and_then(|w| u32::from_str(w.trim()).ok());
+        match (value, weight) {
+            (Some(value), Some(weight)) => Ok((value, weight)),
+            _ => Err(format!("`{}` is not VALUE:WEIGHT", pair)),
+        }
+    }).collect()
+}
+
+fn cli() -> App<'static, 'static> {
+    App::new("game2048")
+        .version(crate_version!())
+        .about("2048 game in Rust")
+        .arg(Arg::with_name("size").long("size").value_name("N|WxH")
+             .help("Board size, square or width x height [default: 4]")
+             .validator(|s| parse_size(&s).map(|_| ())))
+        .arg(Arg::with_name("width").long("width").value_name("N")
+             .help("Board width, overrides --size")
+             .validator(|s| parse_dim(&s).map(|_| ())))
+        .arg(Arg::with_name("height").long("height").value_name("N")
+             .help("Board height, overrides --size")
+             .validator(|s| parse_dim(&s).map(|_| ())))
+        .arg(Arg::with_name("seed").long("seed").value_name("SEED")
+             .help("Seed for tile spawns, the same seed and moves give the same game")
+             .validator(is_number))
+        .arg(Arg::with_name("resume").long("resume")
+             .help("Continue the last unfinished game")
+             .conflicts_with_all(&["size", "width", "height", "seed", "rules", "spawn-values",
+                                   "spawns-per-move", "start-tiles", "spawner", "difficulty",
+                                   "spawn-depth", "evil-chance"]))
+        .arg(Arg::with_name("record").long("record").value_name("FILE")
+             .help("Write a replay of the game to FILE, see the replay subcommand"))
+        .args(&spawn_args())
+        .arg(ui_arg())
+        .arg(animation_arg())
+        .arg(Arg::with_name("log-level").long("log-level").value_name("LEVEL")
+             .help("Log diagnostics to stderr, or to --log-file")
+             .possible_values(&["off", "error", "warn", "info", "debug", "trace"]))
+        .arg(Arg::with_name("log-file").long("log-file").value_name("PATH")
+             .help("Append diagnostics to this file, at info level unless --log-level is given"))
+        .arg(Arg::with_name("data-dir").long("data-dir").value_name("DIR").global(true)
+             .help("Where saved games and statistics are kept [default: $XDG_DATA_HOME/game2048]"))
+        .subcommand(SubCommand::with_name("stats")
+                    .about("Prints high scores and statistics"))
+        .subcommand(SubCommand::with_name("replay")
+                    .about("Plays back a game recorded with --record")
+                    .arg(Arg::with_name("FILE").required(true)
+                         .help("Replay to play back"))
+                    .arg(Arg::with_name("check").long("check")
+                         .help("Only check the replay plays back as recorded"))
+                    .arg(ui_arg())
+                    .arg(animation_arg()))
+        .subcommand(SubCommand::with_name("simulate")
+                    .about("Plays many games without a UI and reports how they went")
+                    .arg(Arg::with_name("games").long("games").value_name("N")
+                         .help("Number of games")
+                         .default_value("100")
+                         .validator(is_number))
+                    .arg(Arg::with_name("player").long("player").value_name("PLAYER")
+                         .help("Strategy playing the games")
+                         .possible_values(sim::STRATEGY_NAMES)
+                         .default_value("random"))
+                    .arg(Arg::with_name("weights").long("weights").value_name("FILE")
+                         .help("Network the trained player plays with, see the train subcommand")
+                         .required_if("player", "trained"))
+                    .arg(Arg::with_name("cache-size").long("cache-size").value_name("N")
+                         .help("Entries of a transposition table shared by all expectimax games, \
+                                which makes results depend on --threads [default: none]")
+                         .validator(is_number))
+                    .arg(Arg::with_name("playout").long("playout").value_name("PLAYOUT")
+                         .help("How the rollout player plays games out [default: random]")
+                         .possible_values(&["random", "greedy"]))
+                    .arg(Arg::with_name("rollouts").long("rollouts").value_name("N")
+                         .help("Playouts per move of the rollout player [default: 100]")
+                         .validator(is_positive))
+                    .arg(Arg::with_name("rollout-ms").long("rollout-ms").value_name("MS")
+                         .help("Time the rollout player spends on a move, instead of --rollouts")
+                         .conflicts_with("rollouts")
+                         .validator(is_number))
+                    .arg(Arg::with_name("rollout-threads").long("rollout-threads").value_name("N")
+                         .help("Threads running the playouts of each rollout game, \
+                                on top of --threads [default: 1]")
+                         .validator(is_positive))
+                    .arg(Arg::with_name("size").long("size").value_name("N|WxH")
+                         .help("Board size, square or width x height")
+                         .default_value("4")
+                         .validator(|s| parse_size(&s).map(|_| ())))
+                    .arg(Arg::with_name("seed").long("seed").value_name("SEED")
+                         .help("Seed of the first game, game i gets SEED + i [default: random]")
+                         .validator(is_number))
+                    .args(&spawn_args())
+                    .arg(Arg::with_name("threads").long("threads").value_name("N")
+                         .help("Worker threads [default: one per CPU]")
+                         .validator(is_positive))
+                    .arg(Arg::with_name("format").long("format").value_name("FORMAT")
+                         .help("Summary table, one CSV line per game, or both as JSON")
+                         .possible_values(&["table", "csv", "json"])
+                         .default_value("table")))
+        .subcommand(SubCommand::with_name("train")
+                    .about("Learns an n-tuple network by temporal difference, for the trained player")
+                    .arg(Arg::with_name("out").long("out").value_name("FILE").required(true)
+                         .help("Checkpoint to write the network to"))
+                    .arg(Arg::with_name("from").long("from").value_name("FILE")
+                         .help("Go on training a saved network")
+                         .conflicts_with_all(&["tuples", "size", "optimistic"]))
+                    .arg(Arg::with_name("games").long("games").value_name("N")
+                         .help("Number of games to learn from")
+                         .default_value("10000")
+                         .validator(is_number))
+                    .arg(Arg::with_name("tuples").long("tuples").value_name("TUPLES")
+                         .help("Tuple shapes: small, large, or cells like 0,1,2,3;4,5,6,7 [default: small]"))
+                    .arg(Arg::with_name("size").long("size").value_name("N|WxH")
+                         .help("Board size, square or width x height [default: 4]")
+                         .validator(|s| parse_size(&s).map(|_| ())))
+                    .arg(Arg::with_name("learning-rate").long("learning-rate").value_name("RATE")
+                         .help("Step size of the updates")
+                         .default_value("0.1")
+                         .validator(|s| match f32::from_str(&s) {
+                             Ok(r) if r > 0.0 => Ok(()),
+                             _ => Err(format!("`{}` is not a positive number", s)),
+                         }))
+                    .arg(Arg::with_name("optimistic").long("optimistic").value_name("VALUE")
+                         .help("Initial value of every board, a high one makes training explore [default: 0]")
+                         .validator(|s| f32::from_str(&s).map(|_| ()).map_err(|_| format!("`{}` is not a number", s))))
+                    .arg(Arg::with_name("seed").long("seed").value_name("SEED")
+                         .help("Seed of the first game, game i gets SEED + i [default: random]")
+                         .validator(is_number))
+                    .arg(Arg::with_name("checkpoint-every").long("checkpoint-every").value_name("N")
+                         .help("Games between two checkpoints and progress reports")
+                         .default_value("1000")
+                         .validator(is_positive))
+                    .args(&spawn_args()))
+}
+
+// Spawn rule options, on top of the rule read from --rules if any.
+fn spawn_args() -> Vec<Arg<'static, 'static>> {
+    vec![
+        Arg::with_name("rules").long("rules").value_name("FILE")
+            .help("Read the spawn rule from a JSON file, see the README"),
+        Arg::with_name("spawn-values").long("spawn-values").value_name("V:W,...")
+            .help("Values of new tiles with their weights [default: 2:9,4:1]")
+            .validator(|s| parse_spawn_values(&s).map(|_| ())),
+        Arg::with_name("spawns-per-move").long("spawns-per-move").value_name("N")
+            .help("Tiles dropped after every move [default: 1]")
+            .validator(is_number),
+        Arg::with_name("start-tiles").long("start-tiles").value_name("N")
+            .help("Tiles on the board at the start [default: 2]")
+            .validator(is_number),
+        Arg::with_name("spawner").long("spawner").value_name("POLICY")
+            .help("Where new tiles go: a random cell, or the worst one for the player [default: uniform]")
+            .possible_values(&["uniform", "evil"]),
+        Arg::with_name("difficulty").long("difficulty").value_name("LEVEL")
+            .help("Strength of the evil spawner [default: normal]")
+            .possible_values(spawn::DIFFICULTY_NAMES),
+        Arg::with_name("spawn-depth").long("spawn-depth").value_name("N")
+            .help("Moves the evil spawner looks ahead, at most 3, overrides --difficulty")
+            .validator(is_number),
+        Arg::with_name("evil-chance").long("evil-chance").value_name("P")
+            .help("Share of evil spawns, the others random, overrides --difficulty")
+            .validator(|s| match f64::from_str(&s) {
+                Ok(p) if p >= 0.0 && p <= 1.0 => Ok(()),
+                _ => Err(format!("`{}` is not between 0 and 1", s)),
+            }),
+    ]
+}
+
+fn spawn_rule(matches: &ArgMatches) -> SpawnRule {
+    let mut rule = match matches.value_of_os("rules") {
+        Some(path) => SpawnRule::load(path).unwrap_or_else(|e| {
+            fail(&format!("can not read rules {}: {}", Path::new(path).display(), e))
+        }),
+        None => SpawnRule::default(),
+    };
+    if let Some(values) = matches.value_of("spawn-values") {
+        rule.values = parse_spawn_values(values).unwrap();
+    }
+    if let Some(n) = matches.value_of("spawns-per-move") {
+        rule.per_move = usize::from_str(n).unwrap();
+    }
+    if let Some(n) = matches.value_of("start-tiles") {
+        rule.start_tiles = usize::from_str(n).unwrap();
+    }
+    let difficulty = matches.value_of("difficulty").map(|d| Difficulty::from_str(d).unwrap());
+    match matches.value_of("spawner") {
+        Some("uniform") => rule.policy = SpawnPolicy::Uniform,
+        Some(_) => rule.policy = difficulty.unwrap_or(Difficulty::Normal).policy(),
+        // without --spawner, the evil options tune an evil rules file
+        None => if let (Some(d), &SpawnPolicy::Evil { .. }) = (difficulty, &rule.policy) {
+            rule.policy = d.policy();
+        },
+    }
+    match rule.policy {
+        SpawnPolicy::Evil { ref mut depth, ref mut chance } => {
+            if let Some(n) = matches.value_of("spawn-depth") {
+                *depth = usize::from_str(n).unwrap();
+            }
+            if let Some(p) = matches.value_of("evil-chance") {
+                *chance = f64::from_str(p).unwrap();
+            }
+        }
+        _ => if ["difficulty", "spawn-depth", "evil-chance"].iter().any(|arg| matches.is_present(arg)) {
+            fail("--difficulty, --spawn-depth and --evil-chance need the evil spawner");
+        },
+    }
+    if let Err(e) = rule.validate() {
+        fail(&e.to_string());
+    }
+    rule
+}
+
+fn ui_arg() -> Arg<'static, 'static> {
+    Arg::with_name("ui").long("ui").value_name("UI")
+        .help("Front end to play with")
+        .possible_values(&ui_names())
+        .default_value(DEFAULT_UI)
+}
+
+fn animation_arg() -> Arg<'static, 'static> {
+    Arg::with_name("animation-ms").long("animation-ms").value_name("MS")
+        .help("Length of move animations, 0 to disable")
+        .default_value("120")
+        .validator(is_number)
+}
+
+// Errors found once arguments are parsed, reported like clap's own.
+fn fail(msg: &str) -> ! {
+    eprintln!("error: {}", msg);
+    process::exit(1)
+}
 
 fn main() {
-    let args = env::args().collect::<Vec<String>>();
+    let matches = cli().get_matches();
+
+    init_logger(&matches);
+
+    match matches.subcommand() {
+        ("stats", Some(sub)) => print_stats(&data_dir(sub)),
+        ("replay", Some(sub)) => replay(sub),
+        ("simulate", Some(sub)) => simulate(sub),
+        ("train", Some(sub)) => train(sub),
+        _ => play(&matches),
+    }
+}
 
-    let size : usize = match args.len() {
-        1 => 4,
-        3 => i64::from_str(args[2].as_ref()).unwrap_or(4) as usize,
+fn init_logger(matches: &ArgMatches) {
+    let log_file = matches.value_of("log-file").map(PathBuf::from);
+    let log_level = match matches.value_of("log-level") {
+        Some(level) => LevelFilter::from_str(level).unwrap(),
+        None if log_file.is_some() => LevelFilter::Info,
+        None => LevelFilter::Off,
+    };
+    if log_level != LevelFilter::Off {
+        if let Err(e) = logger::init(log_level, log_file.as_ref().map(|p| p.as_path())) {
+            fail(&format!("can not open log: {}", e));
+        }
+    }
+}
+
+fn play(matches: &ArgMatches) {
+    let (mut width, mut height) = matches.value_of("size").map_or((4, 4), |s| parse_size(s).unwrap());
+    if let Some(w) = matches.value_of("width") {
+        width = parse_dim(w).unwrap();
+    }
+    if let Some(h) = matches.value_of("height") {
+        height = parse_dim(h).unwrap();
+    }
+    let rng = match matches.value_of("seed") {
+        Some(seed) => GameRng::from_seed(u64::from_str(seed).unwrap()),
+        None => GameRng::new(),
+    };
+    let record = matches.value_of_os("record").map(Path::new);
+
+    let data = data_dir(matches);
+    let save_path = data.join("save.json");
+    let gm = if matches.is_present("resume") {
+        match GameManager::load(&save_path) {
+            Ok(gm) => gm,
+            Err(e) => fail(&format!("can not resume {}: {}", save_path.display(), e)),
+        }
+    } else {
+        let mut gm = GameManager::with_dimensions(width, height, rng);
+        gm.spawn_rule = spawn_rule(matches);
+        gm
+    };
+    if let Err(e) = fs::create_dir_all(&data) {
+        warn!("can not create data dir: {}", e);
+    }
+    info!("starting a {}x{} {} game, seed {}", gm.width, gm.height, gm.spawn_rule.variant(), gm.seed());
+
+    if let Err(e) = run_ui(matches, Session::Game(gm, &data, record)) {
+        fail(&format!("while running game: {}", e));
+    }
+}
+
+// What a front end is started with, left unread by builds without one.
+#[cfg_attr(not(any(feature = "sdl", feature = "tui")), allow(dead_code))]
+enum Session<'a> {
+    // a game, the data dir and where to record it
+    Game(GameManager, &'a Path, Option<&'a Path>),
+    Replay(Playback),
+}
+
+// Runs `session` on the front end picked with --ui.
+fn run_ui(matches: &ArgMatches, session: Session) -> Result<(), String> {
+    let ui_name = matches.value_of("ui").unwrap();
+    #[cfg(feature = "sdl")]
+    let animation = Duration::from_millis(u64::from_str(matches.value_of("animation-ms").unwrap()).unwrap());
+    match (ui_name, session) {
+        #[cfg(feature = "sdl")]
+        ("sdl", Session::Game(gm, data, record)) => ui::run(gm, data, record, animation).map_err(|e| e.to_string()),
+        #[cfg(feature = "sdl")]
+        ("sdl", Session::Replay(playback)) => ui::run_replay(playback, animation).map_err(|e| e.to_string()),
+        #[cfg(feature = "tui")]
+        ("tui", Session::Game(gm, data, record)) => tui::run(gm, data, record).map_err(|e| e.to_string()),
+        #[cfg(feature = "tui")]
+        ("tui", Session::Replay(playback)) => tui::run_replay(playback).map_err(|e| e.to_string()),
+        _ => Err(format!("front end `{}` is not available in this build", ui_name)),
+    }
+}
+
+fn replay(matches: &ArgMatches) {
+    let path = Path::new(matches.value_of_os("FILE").unwrap());
+    let replay = match Replay::load(path) {
+        Ok(replay) => replay,
+        Err(e) => fail(&format!("can not read replay {}: {}", path.display(), e)),
+    };
+    let playback = match Playback::new(replay) {
+        Ok(playback) => playback,
+        Err(e) => fail(&format!("replay {} does not play back: {}", path.display(), e)),
+    };
+    if matches.is_present("check") {
+        let end = playback.replay().verify().unwrap();
+        println!("{}: {} moves, score {}, max tile {}",
+                 path.display(), playback.len(), end.score, end.grid.max_tile());
+        return;
+    }
+    if let Err(e) = run_ui(matches, Session::Replay(playback)) {
+        fail(&format!("while playing back: {}", e));
+    }
+}
+
+fn print_stats(data: &Path) {
+    let stats = match Stats::load(data.join("stats.json")) {
+        Ok(stats) => stats,
+        Err(e) => fail(&format!("can not read statistics: {}", e)),
+    };
+    if stats.records.is_empty() {
+        println!("No game finished yet.");
+        return;
+    }
+    println!("{:<6} {:<10} {:>6} {:>5} {:>8} {:>10} {:>9} {:>8}",
+             "board", "variant", "games", "wins", "best", "avg score", "best tile", "moves");
+    for r in &stats.records {
+        println!("{:<6} {:<10} {:>6} {:>5} {:>8} {:>10.1} {:>9} {:>8}",
+                 format!("{}x{}", r.width, r.height), r.variant, r.games, r.wins,
+                 r.best_score, r.average_score(), r.highest_tile, r.total_moves);
+    }
+}
+
+// Statistics for the front ends, a broken file is reported and started over.
+fn load_stats(data: &Path) -> Stats {
+    Stats::load(data.join("stats.json")).unwrap_or_else(|e| {
+        warn!("can not read statistics, starting afresh: {}", e);
+        Stats::default()
+    })
+}
+
+// To be called once per game, when it is over.
+fn record_game(stats: &mut Stats, gm: &GameManager, data: &Path) {
+    stats.add_game(gm, &gm.spawn_rule.variant());
+    if let Err(e) = stats.save(data.join("stats.json")) {
+        warn!("can not save statistics: {}", e);
+    }
+}
+
+fn simulate(matches: &ArgMatches) {
+    let games = usize::from_str(matches.value_of("games").unwrap()).unwrap();
+    let (width, height) = parse_size(matches.value_of("size").unwrap()).unwrap();
+    let strategy = match matches.value_of("player").unwrap() {
+        "trained" => {
+            let path = Path::new(matches.value_of_os("weights").unwrap());
+            let network = NTupleNetwork::load(path).unwrap_or_else(|e| {
+                fail(&format!("can not read network {}: {}", path.display(), e))
+            });
+            if (network.width(), network.height()) != (width, height) {
+                fail(&format!("network {} plays on {}x{} boards", path.display(),
+                              network.width(), network.height()));
+            }
+            Strategy::Trained(Arc::new(network))
+        }
+        "rollout" => {
+            let playout = match matches.value_of("playout") {
+                Some("greedy") => Playout::Greedy,
+                _ => Playout::Random,
+            };
+            let budget = match (matches.value_of("rollouts"), matches.value_of("rollout-ms")) {
+                (_, Some(ms)) => Budget::Time(Duration::from_millis(u64::from_str(ms).unwrap())),
+                (Some(n), _) => Budget::Rollouts(usize::from_str(n).unwrap()),
+                (None, None) => Budget::Rollouts(100),
+            };
+            let threads = matches.value_of("rollout-threads").map_or(1, |n| usize::from_str(n).unwrap());
+            Strategy::Rollout(playout, budget, threads)
+        }
+        "expectimax" => {
+            let cache = match matches.value_of("cache-size").map(|n| usize::from_str(n).unwrap()) {
+                Some(0) | None => None,
+                Some(n) => Some(Arc::new(TranspositionTable::new(n))),
+            };
+            Strategy::Expectimax(cache)
+        }
+        player => Strategy::from_str(player).unwrap(),
+    };
+    let seed = match matches.value_of("seed") {
+        Some(seed) => u64::from_str(seed).unwrap(),
+        None => GameRng::new().seed(),
+    };
+    let threads = matches.value_of("threads").map_or(num_cpus::get(), |n| usize::from_str(n).unwrap());
+    let rule = spawn_rule(matches);
+
+    info!("simulating {} {}x{} {} games with {} on {} threads, seed {}",
+          games, width, height, rule.variant(), strategy, threads, seed);
+    let (results, summary) = sim::run_batch(&strategy, &rule, width, height, seed, games, threads);
+
+    match matches.value_of("format").unwrap() {
+        "csv" => {
+            println!("seed,score,max_tile,moves,won");
+            for r in &results {
+                println!("{},{},{},{},{}", r.seed, r.score, r.max_tile, r.moves, r.won);
+            }
+        }
+        "json" => {
+            let out = json!({
+                "player": strategy.to_string(),
+                "width": width,
+                "height": height,
+                "variant": rule.variant(),
+                "seed": seed,
+                "summary": summary,
+                "cache": cache_stats(&strategy),
+                "games": results,
+            });
+            println!("{}", serde_json::to_string_pretty(&out).unwrap());
+        }
         _ => {
-            panic!("usage: ./game2048 --size NUM")
+            println!("{} games of {} on {}x{} {}, seed {}", summary.games, strategy, width, height,
+                     rule.variant(), seed);
+            println!("wins: {} ({:.1}%)", summary.wins,
+                     100.0 * summary.wins as f64 / cmp::max(summary.games, 1) as f64);
+            println!("games/sec: {:.1}", summary.games_per_sec);
+            if let Some(stats) = cache_stats(&strategy) {
+                println!("cache: {:.1}% hits, {} of {} entries used", 100.0 * stats.hit_rate(),
+                         stats.entries, stats.capacity);
+            }
+            println!();
+            println!("score      mean {:.1}", summary.mean_score);
+            for (p, score) in &summary.score_percentiles {
+                println!("       p{:<3}  {}", p, score);
+            }
+            println!();
+            println!("moves      mean {:.1}  min {}  max {}",
+                     summary.mean_moves, summary.min_moves, summary.max_moves);
+            println!();
+            println!("max tile   games");
+            for (tile, n) in &summary.max_tiles {
+                println!("{:>8}   {:>5}  {:.1}%", tile, n, 100.0 * *n as f64 / summary.games as f64);
+            }
+        }
+    }
+}
+
+fn cache_stats(strategy: &Strategy) -> Option<CacheStats> {
+    match *strategy {
+        Strategy::Expectimax(Some(ref cache)) => Some(cache.stats()),
+        _ => None,
+    }
+}
+
+fn train(matches: &ArgMatches) {
+    let out = Path::new(matches.value_of_os("out").unwrap());
+    let mut network = match matches.value_of_os("from") {
+        Some(path) => NTupleNetwork::load(path).unwrap_or_else(|e| {
+            fail(&format!("can not read network {}: {}", Path::new(path).display(), e))
+        }),
+        None => {
+            let (width, height) = matches.value_of("size").map_or((4, 4), |s| parse_size(s).unwrap());
+            let tuples = ntuple::parse_tuples(matches.value_of("tuples").unwrap_or("small"), width, height)
+                .unwrap_or_else(|e| fail(&e.to_string()));
+            let init = matches.value_of("optimistic").map_or(0.0, |v| f32::from_str(v).unwrap());
+            NTupleNetwork::new(width, height, tuples, init).unwrap_or_else(|e| fail(&e.to_string()))
         }
     };
+    let games = u64::from_str(matches.value_of("games").unwrap()).unwrap();
+    let learning_rate = f32::from_str(matches.value_of("learning-rate").unwrap()).unwrap();
+    let every = u64::from_str(matches.value_of("checkpoint-every").unwrap()).unwrap();
+    let seed = match matches.value_of("seed") {
+        Some(seed) => u64::from_str(seed).unwrap(),
+        None => GameRng::new().seed(),
+    };
+    let rule = spawn_rule(matches);
+
+    info!("training {:?} on {} {} games, seed {}", network, games, rule.variant(), seed);
+    let mut start = Instant::now();
+    let (mut scores, mut wins, mut best) = (0, 0, 0);
+    for i in 0..games {
+        let mut gm = GameManager::with_dimensions(network.width(), network.height(),
+                                                  GameRng::from_seed(seed.wrapping_add(i)));
+        gm.spawn_rule = rule.clone();
+        gm.history_depth = 0;
+        gm.setup();
+        network.train_game(&mut gm, learning_rate);
+        scores += gm.score;
+        best = cmp::max(best, gm.score);
+        if gm.won {
+            wins += 1;
+        }
+
+        let played = (i + 1) % every;
+        if played == 0 || i + 1 == games {
+            let n = if played == 0 { every } else { played };
+            let elapsed = start.elapsed();
+            let secs = elapsed.as_secs() as f64 + elapsed.subsec_nanos() as f64 / 1e9;
+            println!("games {:>8}  mean score {:>8.0}  best {:>7}  won {:>5.1}%  {:.1} games/sec",
+                     network.games(), scores as f64 / n as f64, best, 100.0 * wins as f64 / n as f64,
+                     n as f64 / secs);
+            if let Err(e) = network.save(out) {
+                fail(&format!("can not save network to {}: {}", out.display(), e));
+            }
+            scores = 0;
+            wins = 0;
+            best = 0;
+            start = Instant::now();
+        }
+    }
+}
 
-    match ui::run(size) {
-        Ok(_) => (),
-        Err(e) => panic!("Error while running game: {}", e),
+// Writes what was played of the game, when asked to with --record.
+fn save_replay(recorder: &Recorder, gm: &GameManager, record: Option<&Path>) {
+    let path = match record {
+        Some(path) => path,
+        None => return,
+    };
+    let replay = recorder.replay(gm);
+    if replay.steps.is_empty() {
+        return;
+    }
+    match replay.save(path) {
+        Ok(_) => info!("replay of {} moves saved to {}", replay.steps.len(), path.display()),
+        Err(e) => warn!("can not save replay to {}: {}", path.display(), e),
     }
+}
 
+// Keep an unfinished game around for `--resume`, forget a finished one.
+fn autosave(gm: &GameManager, playing: bool, data: &Path) {
+    let save_path = data.join("save.json");
+    let save_path = save_path.as_path();
+    if playing {
+        match gm.save(save_path) {
+            Ok(_) => info!("game saved to {}", save_path.display()),
+            Err(e) => warn!("can not save game to {}: {}", save_path.display(), e),
+        }
+    } else if save_path.exists() {
+        let _ = fs::remove_file(save_path);
+    }
 }
@@ -0,0 +1,384 @@
+//! N-tuple value network, learnt by temporal difference.
+//!
+//! The network values afterstates, boards right after a slide and before
+//! the spawn. Each tuple is a list of cells, indexed row-major from the top
+//! left (`y * width + x`); the exponents of its tiles (0 for an empty cell,
+//! capped at 15) index a table of weights. A tuple is looked up at each of
+//! its images by the symmetries of the board, 8 on a square board, 4 on
+//! others, which all share the table. The value of a board is the sum of
+//! the weights looked up.
+//!
+//! Training plays whole games through `GameManager`, with whatever spawn
+//! rule it has, and pulls the value of each afterstate towards the reward
+//! of the next move plus the value of the next afterstate (TD(0), Szubert &
+//! Jaśkowski 2014).
+//!
+//! Checkpoints are binary, all numbers little endian:
+//!
+//! ```text
+//! "2048NTUP"  u32 version  u32 width  u32 height  u64 games
+//! u32 tuples  { u32 len  u32 cell * len } * tuples
+//! { f32 weight * 16^len } * tuples
+//! ```
+
+use std::error::Error;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+use game::{Direction, GameManager, Grid, Symmetry};
+
+/// Current checkpoint format.
+pub const NTUPLE_VERSION: u32 = 1;
+
+const MAGIC: &'static [u8] = b"2048NTUP";
+
+/// Longest tuple, 16^6 weights take 64 MB.
+pub const MAX_TUPLE_LEN: usize = 6;
+
+/// The `(x, y)` cells of a tuple.
+pub type Shape = &'static [(usize, usize)];
+
+/// Tuple shapes by name. `small` is two rows and two squares, `large` the
+/// four 6-tuples of Yeh et al.
+pub static PRESETS: &'static [(&'static str, &'static [Shape])] = &[
+    ("small", &[&[(0, 0), (1, 0), (2, 0), (3, 0)],
+                &[(0, 1), (1, 1), (2, 1), (3, 1)],
+                &[(0, 0), (1, 0), (0, 1), (1, 1)],
+                &[(1, 1), (2, 1), (1, 2), (2, 2)]]),
+    ("large", &[&[(0, 0), (1, 0), (2, 0), (3, 0), (0, 1), (1, 1)],
+                &[(0, 1), (1, 1), (2, 1), (3, 1), (0, 2), (1, 2)],
+                &[(0, 0), (1, 0), (2, 0), (0, 1), (1, 1), (2, 1)],
+                &[(0, 1), (1, 1), (2, 1), (0, 2), (1, 2), (2, 2)]]),
+];
+
+/// Why a network could not be built, saved or loaded.
+#[derive(Debug)]
+pub enum NTupleError {
+    Io(io::Error),
+    /// Not a checkpoint, or a truncated one.
+    Format(String),
+    /// The file was written by an incompatible version.
+    Version(u32),
+    /// A tuple is empty, too long, repeats a cell or leaves the board.
+    Tuple(String),
+}
+
+impl fmt::Display for NTupleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            NTupleError::Io(ref e) => write!(f, "{}", e),
+            NTupleError::Format(ref msg) => write!(f, "malformed checkpoint: {}", msg),
+            NTupleError::Version(v) =>
+                write!(f, "unsupported checkpoint version {} (expected {})", v, NTUPLE_VERSION),
+            NTupleError::Tuple(ref msg) => write!(f, "invalid tuple: {}", msg),
+        }
+    }
+}
+
+impl Error for NTupleError {
+    fn description(&self) -> &str {
+        match *self {
+            NTupleError::Io(_) => "i/o error",
+            NTupleError::Format(_) => "malformed checkpoint",
+            NTupleError::Version(_) => "unsupported checkpoint version",
+            NTupleError::Tuple(_) => "invalid tuple",
+        }
+    }
+}
+
+impl From<io::Error> for NTupleError {
+    fn from(e: io::Error) -> NTupleError {
+        NTupleError::Io(e)
+    }
+}
+
+/// Reads tuples as a preset name, or as cells separated by commas and
+/// tuples by semicolons, e.g. `0,1,2,3;4,5,6,7`.
+pub fn parse_tuples(spec: &str, width: usize, height: usize) -> Result<Vec<Vec<usize>>, NTupleError> {
+    if let Some(&(_, shapes)) = PRESETS.iter().find(|&&(name, _)| name == spec) {
+        return shapes.iter().map(|shape| {
+            shape.iter().map(|&(x, y)| {
+                if x < width && y < height {
+                    Ok(y * width + x)
+                } else {
+                    Err(NTupleError::Tuple(format!("preset `{}` needs a board of 4x3 or more", spec)))
+                }
+            }).collect()
+        }).collect();
+    }
+    spec.split(';').map(|tuple| {
+        tuple.split(',').map(|cell| {
+            cell.trim().parse().map_err(|_| NTupleError::Tuple(format!("`{}` is not a cell", cell.trim())))
+        }).collect()
+    }).collect()
+}
+
+// Cell exponents, row-major.
+fn encode(grid: &Grid, width: usize, height: usize) -> Vec<u8> {
+    let mut board = vec![0; width * height];
+    grid.each_cell(|x, y, tile| {
+        if let Some(tile) = tile {
+            board[y * width + x] = ((tile.value as f64).log2() as u8).min(15);
+        }
+    });
+    board
+}
+
+// The images of every cell by each symmetry of the board.
+fn symmetries(width: usize, height: usize) -> Vec<Vec<usize>> {
+    Symmetry::preserving(width, height).into_iter().map(|sym| {
+        (0..width * height).map(|cell| {
+            let (x, y) = sym.position((cell % width, cell / width), width, height);
+            y * width + x
+        }).collect()
+    }).collect()
+}
+
+/// Weights of an n-tuple network over a `width` x `height` board.
+pub struct NTupleNetwork {
+    width: usize,
+    height: usize,
+    tuples: Vec<Vec<usize>>,
+    // per tuple, its cells under each symmetry
+    images: Vec<Vec<Vec<usize>>>,
+    weights: Vec<Vec<f32>>,
+    games: u64
+}
+
+impl fmt::Debug for NTupleNetwork {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("NTupleNetwork")
+            .field("width", &self.width)
+            .field("height", &self.height)
+            .field("tuples", &self.tuples)
+            .field("games", &self.games)
+            .finish()
+    }
+}
+
+impl NTupleNetwork {
+    /// A network whose every board is worth `init` at first; a high value
+    /// is optimistic and makes training explore.
+    pub fn new(width: usize, height: usize, tuples: Vec<Vec<usize>>, init: f32) -> Result<NTupleNetwork, NTupleError> {
+        let weights = tuples.iter().map(|t| vec![0.0; 1 << (4 * t.len().min(MAX_TUPLE_LEN))]).collect();
+        let mut network = try!(NTupleNetwork::with_weights(width, height, tuples, weights, 0));
+        let init = init / network.lookups() as f32;
+        for table in network.weights.iter_mut() {
+            for w in table.iter_mut() {
+                *w = init;
+            }
+        }
+        Ok(network)
+    }
+
+    fn with_weights(width: usize, height: usize, tuples: Vec<Vec<usize>>, weights: Vec<Vec<f32>>,
+                    games: u64) -> Result<NTupleNetwork, NTupleError> {
+        if tuples.is_empty() {
+            return Err(NTupleError::Tuple("no tuple given".to_string()));
+        }
+        for tuple in &tuples {
+            if tuple.is_empty() || tuple.len() > MAX_TUPLE_LEN {
+                return Err(NTupleError::Tuple(format!("{:?} does not have 1 to {} cells", tuple, MAX_TUPLE_LEN)));
+            }
+            if let Some(&cell) = tuple.iter().find(|&&c| c >= width * height) {
+                return Err(NTupleError::Tuple(format!("cell {} is off a {}x{} board", cell, width, height)));
+            }
+            if tuple.iter().enumerate().any(|(i, c)| tuple[..i].contains(c)) {
+                return Err(NTupleError::Tuple(format!("{:?} repeats a cell", tuple)));
+            }
+        }
+        let syms = symmetries(width, height);
+        let images = tuples.iter().map(|tuple| {
+            syms.iter().map(|sym| tuple.iter().map(|&c| sym[c]).collect()).collect()
+        }).collect();
+        Ok(NTupleNetwork { width: width, height: height, tuples: tuples, images: images,
+                           weights: weights, games: games })
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn tuples(&self) -> &[Vec<usize>] {
+        &self.tuples
+    }
+
+    /// Games trained so far.
+    pub fn games(&self) -> u64 {
+        self.games
+    }
+
+    // weights looked up per board
+    fn lookups(&self) -> usize {
+        self.images.iter().map(|images| images.len()).sum()
+    }
+
+    fn index(board: &[u8], cells: &[usize]) -> usize {
+        cells.iter().fold(0, |index, &c| (index << 4) | board[c] as usize)
+    }
+
+    fn board_value(&self, board: &[u8]) -> f32 {
+        self.images.iter().zip(self.weights.iter()).map(|(images, table)| {
+            images.iter().map(|cells| table[NTupleNetwork::index(board, cells)]).sum::<f32>()
+        }).sum()
+    }
+
+    fn adjust(&mut self, board: &[u8], delta: f32) {
+        for (images, table) in self.images.iter().zip(self.weights.iter_mut()) {
+            for cells in images {
+                table[NTupleNetwork::index(board, cells)] += delta;
+            }
+        }
+    }
+
+    /// Value of an afterstate.
+    pub fn value(&self, grid: &Grid) -> f32 {
+        self.board_value(&encode(grid, self.width, self.height))
+    }
+
+    // The move with the most reward plus afterstate value, with the
+    // encoded afterstate and that sum.
+    fn best_afterstate(&self, grid: &Grid) -> Option<(Direction, Vec<u8>, f32)> {
+        let mut best: Option<(Direction, Vec<u8>, f32)> = None;
+        for dir in Direction::all_directions() {
+            if let Some((after, reward)) = grid.slide(dir) {
+                let board = encode(&after, self.width, self.height);
+                let value = reward as f32 + self.board_value(&board);
+                if !best.as_ref().is_some_and(|&(_, _, v)| v >= value) {
+                    best = Some((dir, board, value));
+                }
+            }
+        }
+        best
+    }
+
+    /// Greedy move on `grid`, `None` if stuck or if the board is not the
+    /// network's size.
+    pub fn best_move(&self, grid: &Grid) -> Option<Direction> {
+        if grid.width != self.width || grid.height != self.height {
+            return None;
+        }
+        self.best_afterstate(grid).map(|(dir, _, _)| dir)
+    }
+
+    /// Plays `gm` greedily to the end, past the target tile, learning from
+    /// every move at `learning_rate`.
+    pub fn train_game(&mut self, gm: &mut GameManager, learning_rate: f32) {
+        gm.keep_playing();
+        let rate = learning_rate / self.lookups() as f32;
+        let mut last: Option<Vec<u8>> = None;
+        loop {
+            let best = self.best_afterstate(&gm.grid);
+            // a lost game is worth nothing more
+            let target = best.as_ref().map_or(0.0, |&(_, _, value)| value);
+            if let Some(board) = last.take() {
+                let error = target - self.board_value(&board);
+                self.adjust(&board, rate * error);
+            }
+            match best {
+                Some((dir, board, _)) => {
+                    gm.move_to(dir);
+                    last = Some(board);
+                }
+                None => break,
+            }
+        }
+        self.games += 1;
+    }
+
+    /// Writes a checkpoint, through a temporary file so that an interrupted
+    /// write leaves the last one whole.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), NTupleError> {
+        let path = path.as_ref();
+        let tmp = path.with_extension("tmp");
+        {
+            let mut out = BufWriter::new(try!(File::create(&tmp)));
+            try!(out.write_all(MAGIC));
+            try!(write_u32(&mut out, NTUPLE_VERSION));
+            try!(write_u32(&mut out, self.width as u32));
+            try!(write_u32(&mut out, self.height as u32));
+            try!(write_u32(&mut out, self.games as u32));
+            try!(write_u32(&mut out, (self.games >> 32) as u32));
+            try!(write_u32(&mut out, self.tuples.len() as u32));
+            for tuple in &self.tuples {
+                try!(write_u32(&mut out, tuple.len() as u32));
+                for &cell in tuple {
+                    try!(write_u32(&mut out, cell as u32));
+                }
+            }
+            for table in &self.weights {
+                for &w in table {
+                    try!(write_u32(&mut out, w.to_bits()));
+                }
+            }
+            try!(out.flush());
+        }
+        try!(fs::rename(&tmp, path));
+        Ok(())
+    }
+
+    /// Reads back a checkpoint written by `save()`.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<NTupleNetwork, NTupleError> {
+        let mut input = BufReader::new(try!(File::open(path)));
+        let mut magic = [0; 8];
+        try!(read_exact(&mut input, &mut magic));
+        if &magic[..] != MAGIC {
+            return Err(NTupleError::Format("not an n-tuple checkpoint".to_string()));
+        }
+        let version = try!(read_u32(&mut input));
+        if version != NTUPLE_VERSION {
+            return Err(NTupleError::Version(version));
+        }
+        let width = try!(read_u32(&mut input)) as usize;
+        let height = try!(read_u32(&mut input)) as usize;
+        let games = try!(read_u32(&mut input)) as u64 | (try!(read_u32(&mut input)) as u64) << 32;
+        if width == 0 || height == 0 {
+            return Err(NTupleError::Format(format!("{}x{} board", width, height)));
+        }
+        let count = try!(read_u32(&mut input)) as usize;
+        let mut tuples = vec![];
+        for _ in 0..count {
+            let len = try!(read_u32(&mut input)) as usize;
+            if len > MAX_TUPLE_LEN {
+                return Err(NTupleError::Tuple(format!("{} cells, at most {}", len, MAX_TUPLE_LEN)));
+            }
+            let mut tuple = vec![];
+            for _ in 0..len {
+                tuple.push(try!(read_u32(&mut input)) as usize);
+            }
+            tuples.push(tuple);
+        }
+        let mut weights = vec![];
+        for tuple in &tuples {
+            let mut table = Vec::with_capacity(1 << (4 * tuple.len()));
+            for _ in 0..1 << (4 * tuple.len()) {
+                table.push(f32::from_bits(try!(read_u32(&mut input))));
+            }
+            weights.push(table);
+        }
+        NTupleNetwork::with_weights(width, height, tuples, weights, games)
+    }
+}
+
+fn write_u32<W: Write>(out: &mut W, n: u32) -> io::Result<()> {
+    out.write_all(&[n as u8, (n >> 8) as u8, (n >> 16) as u8, (n >> 24) as u8])
+}
+
+fn read_exact<R: Read>(input: &mut R, buf: &mut [u8]) -> Result<(), NTupleError> {
+    input.read_exact(buf).map_err(|e| match e.kind() {
+        io::ErrorKind::UnexpectedEof => NTupleError::Format("truncated file".to_string()),
+        _ => NTupleError::Io(e),
+    })
+}
+
+fn read_u32<R: Read>(input: &mut R) -> Result<u32, NTupleError> {
+    let mut buf = [0; 4];
+    try!(read_exact(input, &mut buf));
+    Ok(buf[0] as u32 | (buf[1] as u32) << 8 | (buf[2] as u32) << 16 | (buf[3] as u32) << 24)
+}
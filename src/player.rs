@@ -0,0 +1,249 @@
+//! Agents playing a game, human or not.
+//!
+//! A `Player` picks moves from a `GameView`, which shows the board and the
+//! score but not the random source, so no player can foresee spawns. A
+//! `GameRunner` drives any player against a `GameManager`; front ends feed a
+//! `HumanPlayer` with key presses, batch runs use the computer players.
+
+use std::collections::VecDeque;
+use std::path::Path;
+use std::sync::Arc;
+
+use rand::{Rng, SeedableRng};
+
+use ai::{Expectimax, Heuristic};
+use game::{Direction, GameManager, Grid, MoveOutcome};
+use ntuple::{NTupleNetwork, NTupleError};
+use rng::GameRng;
+use spawn::SpawnRule;
+
+/// What a player sees of a game.
+#[derive(Debug, Clone, Copy)]
+pub struct GameView<'a> {
+    gm: &'a GameManager
+}
+
+impl<'a> GameView<'a> {
+    pub fn new(gm: &'a GameManager) -> GameView<'a> {
+        GameView { gm: gm }
+    }
+
+    pub fn grid(&self) -> &'a Grid {
+        &self.gm.grid
+    }
+
+    pub fn score(&self) -> usize {
+        self.gm.score
+    }
+
+    /// Moves played so far.
+    pub fn moves(&self) -> usize {
+        self.gm.moves
+    }
+
+    pub fn won(&self) -> bool {
+        self.gm.won
+    }
+
+    /// How tiles spawn in this game.
+    pub fn spawn_rule(&self) -> &'a SpawnRule {
+        &self.gm.spawn_rule
+    }
+
+    /// A copy of the game drawing its spawns from `rng` rather than the
+    /// real source, for players searching ahead.
+    pub fn fork(&self, rng: GameRng) -> GameManager {
+        self.gm.fork(rng)
+    }
+
+    /// Directions that move at least one tile.
+    pub fn legal_moves(&self) -> Vec<Direction> {
+        Direction::all_directions().into_iter().filter(|&dir| self.gm.grid.slide(dir).is_some()).collect()
+    }
+}
+
+pub trait Player {
+    /// Next move, `None` when the player has none to offer right now.
+    fn choose(&mut self, view: &GameView) -> Option<Direction>;
+}
+
+impl<P: Player + ?Sized> Player for Box<P> {
+    fn choose(&mut self, view: &GameView) -> Option<Direction> {
+        (**self).choose(view)
+    }
+}
+
+/// Moves typed by a person, played in order.
+#[derive(Debug, Clone, Default)]
+pub struct HumanPlayer {
+    pending: VecDeque<Direction>
+}
+
+impl HumanPlayer {
+    pub fn new() -> HumanPlayer {
+        HumanPlayer::default()
+    }
+
+    /// Queues a move, e.g. from a key press.
+    pub fn push(&mut self, dir: Direction) {
+        self.pending.push_back(dir);
+    }
+
+    /// Forgets the moves not played yet.
+    pub fn clear(&mut self) {
+        self.pending.clear();
+    }
+
+    pub fn has_pending(&self) -> bool {
+        !self.pending.is_empty()
+    }
+}
+
+impl Player for HumanPlayer {
+    fn choose(&mut self, _: &GameView) -> Option<Direction> {
+        self.pending.pop_front()
+    }
+}
+
+/// Plays a fixed list of moves, legal or not, then stops.
+#[derive(Debug, Clone)]
+pub struct ScriptedPlayer {
+    moves: VecDeque<Direction>
+}
+
+impl ScriptedPlayer {
+    pub fn new(moves: Vec<Direction>) -> ScriptedPlayer {
+        ScriptedPlayer { moves: moves.into_iter().collect() }
+    }
+}
+
+impl Player for ScriptedPlayer {
+    fn choose(&mut self, _: &GameView) -> Option<Direction> {
+        self.moves.pop_front()
+    }
+}
+
+/// Any legal move, drawn from an rng of its own.
+#[derive(Debug, Clone)]
+pub struct RandomPlayer {
+    rng: GameRng
+}
+
+impl RandomPlayer {
+    pub fn new(seed: u64) -> RandomPlayer {
+        RandomPlayer { rng: GameRng::from_seed(seed) }
+    }
+}
+
+impl Player for RandomPlayer {
+    fn choose(&mut self, view: &GameView) -> Option<Direction> {
+        let legal = view.legal_moves();
+        if legal.is_empty() {
+            None
+        } else {
+            Some(legal[self.rng.gen_range(0, legal.len())])
+        }
+    }
+}
+
+/// The legal move scoring the most points, then leaving the most empty
+/// cells.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct GreedyPlayer;
+
+impl Player for GreedyPlayer {
+    fn choose(&mut self, view: &GameView) -> Option<Direction> {
+        Direction::all_directions().into_iter()
+            .filter_map(|dir| view.grid().slide(dir).map(|(after, score)| (dir, after, score)))
+            .max_by_key(|&(_, ref after, score)| (score, after.available_cells().len()))
+            .map(|(dir, _, _)| dir)
+    }
+}
+
+/// Keeps the biggest tiles in the bottom left corner: down, left, right,
+/// up, whichever is first legal.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CornerPlayer;
+
+impl Player for CornerPlayer {
+    fn choose(&mut self, view: &GameView) -> Option<Direction> {
+        let legal = view.legal_moves();
+        [Direction::Down, Direction::Left, Direction::Right, Direction::Up].iter().cloned()
+            .find(|dir| legal.contains(dir))
+    }
+}
+
+impl<H: Heuristic> Player for Expectimax<H> {
+    fn choose(&mut self, view: &GameView) -> Option<Direction> {
+        self.best_move_on(view.grid(), view.spawn_rule())
+    }
+}
+
+/// The move an n-tuple network trained with `train` values most. Sharing
+/// the network, many players cost little more than one.
+#[derive(Debug, Clone)]
+pub struct TrainedPlayer {
+    network: Arc<NTupleNetwork>
+}
+
+impl TrainedPlayer {
+    pub fn new(network: Arc<NTupleNetwork>) -> TrainedPlayer {
+        TrainedPlayer { network: network }
+    }
+
+    /// Plays with the weights of a checkpoint.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<TrainedPlayer, NTupleError> {
+        NTupleNetwork::load(path).map(|network| TrainedPlayer::new(Arc::new(network)))
+    }
+
+    pub fn network(&self) -> &NTupleNetwork {
+        &self.network
+    }
+}
+
+impl Player for TrainedPlayer {
+    /// `None` on a board of another size than the network's.
+    fn choose(&mut self, view: &GameView) -> Option<Direction> {
+        self.network.best_move(view.grid())
+    }
+}
+
+/// Drives a player against a game.
+#[derive(Debug)]
+pub struct GameRunner<P> {
+    pub gm: GameManager,
+    pub player: P
+}
+
+impl<P: Player> GameRunner<P> {
+    pub fn new(gm: GameManager, player: P) -> GameRunner<P> {
+        GameRunner { gm: gm, player: player }
+    }
+
+    /// The player's next move, without playing it. `None` if the game has
+    /// stopped or the player has no move.
+    pub fn next_move(&mut self) -> Option<Direction> {
+        if self.gm.is_terminated() {
+            return None;
+        }
+        self.player.choose(&GameView::new(&self.gm))
+    }
+
+    /// Plays the player's next move. `None` if the game has stopped or the
+    /// player has no move, an illegal move comes back with `moved()` false.
+    pub fn step(&mut self) -> Option<(Direction, MoveOutcome)> {
+        self.next_move().map(|dir| (dir, self.gm.move_to(dir)))
+    }
+
+    /// Plays until the game stops or the player has no move left. Illegal
+    /// moves end the run too, a player could repeat them forever.
+    pub fn run(&mut self) -> &GameManager {
+        loop {
+            match self.step() {
+                Some((_, ref outcome)) if outcome.moved() => (),
+                _ => break,
+            }
+        }
+        &self.gm
+    }
+}
@@ -0,0 +1,385 @@
+//! Recording and playing back games.
+//!
+//! A replay holds the board a game started from, the rng right after the
+//! start tiles were dropped and the spawn rule, then every move with the
+//! tiles it spawned. Moves are stored as a string of `U`, `R`, `D`, `L`,
+//! which keeps files small:
+//!
+//! ```json
+//! {"version": 2, "width": 4, "height": 4, "target_tile": 2048, "score": 0,
+//!  "rng": {...}, "spawn_rule": {...}, "spawn_count": 2,
+//!  "start": [[0, 1, 2], [3, 2, 2]],
+//!  "moves": "LLUR", "spawns": [[[3, 0, 2]], [[3, 3, 4]], [[0, 3, 2]], [[0, 0, 2]]]}
+//! ```
+//!
+//! Spawns follow from the rng, they are kept so that `verify()` can tell a
+//! replay that no longer plays the same, e.g. after a change of the rules.
+//! Version 1 files, from before several tiles could spawn per move, are
+//! still read.
+
+use std::error::Error;
+use std::fmt;
+use std::fs::File;
+use std::io;
+use std::path::Path;
+
+use serde_json::{self, Value};
+
+pub use game::TileSpec;
+use game::{Direction, GameManager, Grid, MoveOutcome, Tile};
+use rng::GameRng;
+use save::{SaveError, validate_size};
+use spawn::SpawnRule;
+
+/// Current replay file format.
+pub const REPLAY_VERSION: u32 = 2;
+
+/// Bounds of the delay between two moves front ends play back, in
+/// milliseconds.
+pub const MIN_INTERVAL_MS: u64 = 25;
+pub const MAX_INTERVAL_MS: u64 = 4000;
+
+fn tile_spec(tile: &Tile) -> TileSpec {
+    (tile.x, tile.y, tile.value)
+}
+
+/// One recorded move.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Step {
+    pub dir: Direction,
+    /// Tiles dropped after the move.
+    pub spawned: Vec<TileSpec>
+}
+
+/// A game from its first recorded position to its last move.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Replay {
+    pub width: usize,
+    pub height: usize,
+    pub target_tile: isize,
+    /// Score before the first move, not 0 when recording a resumed game.
+    pub score: usize,
+    pub rng: GameRng,
+    pub spawn_rule: SpawnRule,
+    /// Tiles spawned before the first move, see `GameManager::spawn_count`.
+    pub spawn_count: usize,
+    pub start: Vec<TileSpec>,
+    pub steps: Vec<Step>
+}
+
+#[derive(Serialize, Deserialize)]
+struct ReplayFile {
+    version: u32,
+    width: usize,
+    height: usize,
+    target_tile: isize,
+    score: usize,
+    rng: GameRng,
+    #[serde(default)]
+    spawn_rule: SpawnRule,
+    #[serde(default)]
+    spawn_count: usize,
+    start: Vec<TileSpec>,
+    moves: String,
+    spawns: Vec<Vec<TileSpec>>
+}
+
+/// Why a replay could not be read, or does not play back.
+#[derive(Debug)]
+pub enum ReplayError {
+    Io(io::Error),
+    Format(serde_json::Error),
+    /// The file was written by an incompatible version.
+    Version(u32),
+    /// Not one of `U`, `R`, `D`, `L`.
+    Move(char),
+    /// There is not one spawn per move.
+    SpawnCount(usize, usize),
+    /// A start tile is off the board or not a power of two.
+    StartTile(TileSpec),
+    /// The game it starts from would not load from a save file.
+    Game(SaveError),
+    /// The move at this index does not move anything.
+    IllegalMove(usize),
+    /// The move at this index spawned other tiles than the recorded ones.
+    Diverged(usize, Vec<TileSpec>, Vec<TileSpec>),
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ReplayError::Io(ref e) => write!(f, "{}", e),
+            ReplayError::Format(ref e) => write!(f, "malformed replay: {}", e),
+            ReplayError::Version(v) =>
+                write!(f, "unsupported replay version {} (expected {})", v, REPLAY_VERSION),
+            ReplayError::Move(c) => write!(f, "unknown move {:?}", c),
+            ReplayError::SpawnCount(moves, spawns) =>
+                write!(f, "{} moves but {} spawns", moves, spawns),
+            ReplayError::StartTile(tile) => write!(f, "invalid start tile {:?}", tile),
+            ReplayError::Game(ref e) => write!(f, "invalid start position: {}", e),
+            ReplayError::IllegalMove(i) => write!(f, "move {} does not move anything", i + 1),
+            ReplayError::Diverged(i, ref expected, ref got) =>
+                write!(f, "move {} spawned {:?}, recorded {:?}", i + 1, got, expected),
+        }
+    }
+}
+
+impl Error for ReplayError {
+    fn description(&self) -> &str {
+        match *self {
+            ReplayError::Io(_) => "i/o error",
+            ReplayError::Format(_) => "malformed replay",
+            ReplayError::Version(_) => "unsupported replay version",
+            ReplayError::Move(_) => "unknown move",
+            ReplayError::SpawnCount(..) => "spawn count mismatch",
+            ReplayError::StartTile(_) => "invalid start tile",
+            ReplayError::Game(_) => "invalid start position",
+            ReplayError::IllegalMove(_) => "illegal move",
+            ReplayError::Diverged(..) => "replay diverged",
+        }
+    }
+}
+
+impl From<io::Error> for ReplayError {
+    fn from(e: io::Error) -> ReplayError {
+        ReplayError::Io(e)
+    }
+}
+
+impl From<serde_json::Error> for ReplayError {
+    fn from(e: serde_json::Error) -> ReplayError {
+        ReplayError::Format(e)
+    }
+}
+
+impl From<SaveError> for ReplayError {
+    fn from(e: SaveError) -> ReplayError {
+        ReplayError::Game(e)
+    }
+}
+
+fn move_char(dir: Direction) -> char {
+    match dir {
+        Direction::Up => 'U',
+        Direction::Right => 'R',
+        Direction::Down => 'D',
+        Direction::Left => 'L',
+    }
+}
+
+fn char_move(c: char) -> Result<Direction, ReplayError> {
+    match c {
+        'U' => Ok(Direction::Up),
+        'R' => Ok(Direction::Right),
+        'D' => Ok(Direction::Down),
+        'L' => Ok(Direction::Left),
+        _ => Err(ReplayError::Move(c)),
+    }
+}
+
+impl Replay {
+    /// Game at the first recorded position.
+    pub fn game(&self) -> GameManager {
+        let mut gm = GameManager::with_dimensions(self.width, self.height, self.rng);
+        gm.spawn_rule = self.spawn_rule.clone();
+        gm.spawn_count = self.spawn_count;
+        gm.target_tile = self.target_tile;
+        gm.score = self.score;
+        gm.playing = true;
+        for &(x, y, value) in &self.start {
+            gm.grid.insert_tile(Tile::new((x, y), value));
+        }
+        gm
+    }
+
+    /// Plays every move again, checking each one moves and spawns the
+    /// recorded tile. Returns the final game.
+    pub fn verify(&self) -> Result<GameManager, ReplayError> {
+        let mut gm = self.game();
+        for (i, step) in self.steps.iter().enumerate() {
+            let outcome = gm.move_to(step.dir);
+            if !outcome.moved() {
+                return Err(ReplayError::IllegalMove(i));
+            }
+            let spawned = outcome.spawned.iter().map(tile_spec).collect::<Vec<_>>();
+            if spawned != step.spawned {
+                return Err(ReplayError::Diverged(i, step.spawned.clone(), spawned));
+            }
+        }
+        Ok(gm)
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), ReplayError> {
+        let file = ReplayFile {
+            version: REPLAY_VERSION,
+            width: self.width,
+            height: self.height,
+            target_tile: self.target_tile,
+            score: self.score,
+            rng: self.rng,
+            spawn_rule: self.spawn_rule.clone(),
+            spawn_count: self.spawn_count,
+            start: self.start.clone(),
+            moves: self.steps.iter().map(|s| move_char(s.dir)).collect(),
+            spawns: self.steps.iter().map(|s| s.spawned.clone()).collect(),
+        };
+        try!(serde_json::to_writer(try!(File::create(path)), &file));
+        Ok(())
+    }
+
+    /// Reads a replay written by `save()`, checking the game it starts from
+    /// like `GameManager::load()` does. The moves are not played, see
+    /// `verify()`.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Replay, ReplayError> {
+        let mut file: Value = try!(serde_json::from_reader(try!(File::open(path))));
+        let version = file.get("version").and_then(Value::as_u64).unwrap_or(0) as u32;
+        match version {
+            1 => upgrade_v1(&mut file),
+            REPLAY_VERSION => (),
+            _ => return Err(ReplayError::Version(version)),
+        }
+        let file: ReplayFile = try!(serde_json::from_value(file));
+        try!(validate_size(file.width, file.height));
+        let grid = Grid::with_dimensions(file.width, file.height);
+        for &(x, y, value) in &file.start {
+            if !grid.within_bounds((x, y)) || value < 2 || value & (value - 1) != 0 {
+                return Err(ReplayError::StartTile((x, y, value)));
+            }
+        }
+        let dirs = try!(file.moves.chars().map(char_move).collect::<Result<Vec<_>, _>>());
+        if dirs.len() != file.spawns.len() {
+            return Err(ReplayError::SpawnCount(dirs.len(), file.spawns.len()));
+        }
+        let steps = dirs.into_iter().zip(file.spawns)
+            .map(|(dir, spawned)| Step { dir: dir, spawned: spawned })
+            .collect();
+        let replay = Replay { width: file.width, height: file.height, target_tile: file.target_tile,
+                              score: file.score, rng: file.rng, spawn_rule: file.spawn_rule,
+                              spawn_count: file.spawn_count, start: file.start, steps: steps };
+        try!(replay.game().validate());
+        Ok(replay)
+    }
+}
+
+// v1 moves spawned one tile or none.
+fn upgrade_v1(file: &mut Value) {
+    if let Some(spawns) = file.get_mut("spawns").and_then(Value::as_array_mut) {
+        for spawn in spawns.iter_mut() {
+            *spawn = match spawn.take() {
+                Value::Null => Value::Array(vec![]),
+                tile => Value::Array(vec![tile]),
+            };
+        }
+    }
+    file["version"] = Value::from(REPLAY_VERSION);
+}
+
+/// Records a game as it is played, undos included: a move made after an
+/// undo replaces the undone ones.
+#[derive(Debug, Clone)]
+pub struct Recorder {
+    replay: Replay,
+    // `moves` of the game when recording started
+    base: usize
+}
+
+impl Recorder {
+    /// Starts recording from the current position of `gm`.
+    pub fn new(gm: &GameManager) -> Recorder {
+        let mut start = vec![];
+        gm.grid.each_cell(|_, _, tile| if let Some(tile) = tile {
+            start.push(tile_spec(tile));
+        });
+        let replay = Replay { width: gm.width, height: gm.height, target_tile: gm.target_tile,
+                              score: gm.score, rng: *gm.rng(), spawn_rule: gm.spawn_rule.clone(),
+                              spawn_count: gm.spawn_count, start: start, steps: vec![] };
+        Recorder { replay: replay, base: gm.moves }
+    }
+
+    /// Adds the move `gm` just played.
+    pub fn record(&mut self, gm: &GameManager, dir: Direction, outcome: &MoveOutcome) {
+        if !outcome.moved() {
+            return;
+        }
+        let played = gm.moves.saturating_sub(self.base + 1);
+        self.replay.steps.truncate(played);
+        self.replay.steps.push(Step { dir: dir, spawned: outcome.spawned.iter().map(tile_spec).collect() });
+    }
+
+    /// Replay up to the current position of `gm`, undone moves left out.
+    pub fn replay(&self, gm: &GameManager) -> Replay {
+        let mut replay = self.replay.clone();
+        replay.steps.truncate(gm.moves.saturating_sub(self.base));
+        replay
+    }
+}
+
+/// Walks through a verified replay.
+#[derive(Debug)]
+pub struct Playback {
+    replay: Replay,
+    gm: GameManager,
+    position: usize
+}
+
+impl Playback {
+    /// Verifies the replay, fails if it does not play back as recorded.
+    pub fn new(replay: Replay) -> Result<Playback, ReplayError> {
+        try!(replay.verify());
+        let mut gm = replay.game();
+        gm.history_depth = replay.steps.len();
+        Ok(Playback { replay: replay, gm: gm, position: 0 })
+    }
+
+    pub fn game(&self) -> &GameManager {
+        &self.gm
+    }
+
+    pub fn replay(&self) -> &Replay {
+        &self.replay
+    }
+
+    /// Number of moves played so far.
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    pub fn len(&self) -> usize {
+        self.replay.steps.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.replay.steps.is_empty()
+    }
+
+    /// Plays the next move, `None` at the end.
+    pub fn step_forward(&mut self) -> Option<MoveOutcome> {
+        let step = match self.replay.steps.get(self.position) {
+            Some(step) => step.dir,
+            None => return None,
+        };
+        self.position += 1;
+        Some(self.gm.move_to(step))
+    }
+
+    /// Takes back the last move, `false` at the start.
+    pub fn step_back(&mut self) -> bool {
+        if self.position > 0 && self.gm.undo() {
+            self.position -= 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Goes to the position after `position` moves, clamped to the replay.
+    pub fn seek(&mut self, position: usize) {
+        let position = position.min(self.len());
+        while self.position > position {
+            self.step_back();
+        }
+        while self.position < position {
+            self.step_forward();
+        }
+    }
+}
@@ -0,0 +1,62 @@
+//! Seedable random source for tile spawns.
+
+use rand::{self, Rng, SeedableRng};
+
+/// Small xorshift64* generator.
+///
+/// Its whole state is a single `u64`, so it is cheap to copy into snapshots
+/// and a game started from the same seed always spawns the same tiles.
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct GameRng {
+    seed: u64,
+    state: u64,
+}
+
+impl GameRng {
+    /// Generator seeded from the thread-local random source.
+    pub fn new() -> GameRng {
+        GameRng::from_seed(rand::random::<u64>())
+    }
+
+    /// The seed this generator was created (or last reseeded) with.
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Whether the generator can run, xorshift stays at 0 forever. Only a
+    /// state read from a file can be 0.
+    pub fn is_valid(&self) -> bool {
+        self.state != 0
+    }
+}
+
+impl Rng for GameRng {
+    fn next_u32(&mut self) -> u32 {
+        (self.next_u64() >> 32) as u32
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        self.state.wrapping_mul(0x2545F4914F6CDD1D)
+    }
+}
+
+impl SeedableRng<u64> for GameRng {
+    fn reseed(&mut self, seed: u64) {
+        // splitmix64 scramble, xorshift must never start from 0
+        let mut z = seed.wrapping_add(0x9E3779B97F4A7C15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+        z = z ^ (z >> 31);
+        self.seed = seed;
+        self.state = if z == 0 { 0x9E3779B97F4A7C15 } else { z };
+    }
+
+    fn from_seed(seed: u64) -> GameRng {
+        let mut rng = GameRng { seed: seed, state: 0 };
+        rng.reseed(seed);
+        rng
+    }
+}
@@ -0,0 +1,133 @@
+//! Monte Carlo rollout player, a baseline free of any board heuristic.
+//!
+//! Every legal move is tried on forks of the game, each drawing spawns from
+//! an rng of the player's own, then played out to the end by a random or
+//! greedy playout. The move whose playouts end with the best mean score is
+//! chosen.
+
+use std::thread;
+use std::time::{Duration, Instant};
+
+use rand::{Rng, SeedableRng};
+
+use game::{Direction, GameManager};
+use player::{Player, GameView, GameRunner, RandomPlayer, GreedyPlayer};
+use rng::GameRng;
+
+/// How playouts pick their moves.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Playout {
+    Random,
+    Greedy,
+}
+
+/// How much search goes into one move.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Budget {
+    /// Playouts per legal move.
+    Rollouts(usize),
+    /// Time to spend on the whole move, at least one playout per legal move.
+    Time(Duration),
+}
+
+/// Rollout searcher, see the module doc.
+#[derive(Debug, Clone)]
+pub struct RolloutPlayer {
+    pub playout: Playout,
+    pub budget: Budget,
+    /// Threads running playouts, 1 plays them on the caller's.
+    pub threads: usize,
+    rng: GameRng
+}
+
+impl RolloutPlayer {
+    /// 100 random playouts per move on one thread, `seed` feeding the
+    /// spawns and moves of the playouts.
+    pub fn new(seed: u64) -> RolloutPlayer {
+        RolloutPlayer { playout: Playout::Random, budget: Budget::Rollouts(100), threads: 1,
+                        rng: GameRng::from_seed(seed) }
+    }
+
+    /// Mean final score of the playouts after each legal move.
+    pub fn evaluate(&mut self, view: &GameView) -> Vec<(Direction, f64)> {
+        let legal = view.legal_moves();
+        if legal.is_empty() {
+            return vec![];
+        }
+        let deadline = match self.budget {
+            Budget::Time(limit) => Some(Instant::now() + limit),
+            Budget::Rollouts(_) => None,
+        };
+        let threads = self.threads.max(1);
+        // every thread plays its share of the playouts of every move
+        let shares = (0..threads).map(|t| match self.budget {
+            Budget::Rollouts(n) => Some(n / threads + if t < n % threads { 1 } else { 0 }),
+            Budget::Time(_) => None,
+        }).collect::<Vec<_>>();
+        let seeds = (0..threads).map(|_| self.rng.next_u64()).collect::<Vec<_>>();
+        let base = view.fork(GameRng::from_seed(0));
+
+        let totals = if threads == 1 {
+            vec![rollouts(&base, &legal, self.playout, shares[0], deadline, seeds[0])]
+        } else {
+            let workers = shares.into_iter().zip(seeds).map(|(share, seed)| {
+                let base = base.clone();
+                let legal = legal.clone();
+                let playout = self.playout;
+                thread::spawn(move || rollouts(&base, &legal, playout, share, deadline, seed))
+            }).collect::<Vec<_>>();
+            workers.into_iter().map(|w| w.join().unwrap()).collect()
+        };
+
+        legal.iter().enumerate().map(|(i, &dir)| {
+            let (sum, count) = totals.iter().fold((0.0, 0), |(sum, count), t| (sum + t[i].0, count + t[i].1));
+            (dir, if count == 0 { 0.0 } else { sum / count as f64 })
+        }).collect()
+    }
+}
+
+// Plays `share` playouts after each of `legal`, or round after round until
+// `deadline` (the first round always runs), as (score sum, playouts) per
+// move.
+fn rollouts(base: &GameManager, legal: &[Direction], playout: Playout, share: Option<usize>,
+            deadline: Option<Instant>, seed: u64) -> Vec<(f64, usize)> {
+    let mut rng = GameRng::from_seed(seed);
+    let mut totals = vec![(0.0, 0); legal.len()];
+    let mut round = 0;
+    loop {
+        match (share, deadline) {
+            (Some(n), _) if round >= n => break,
+            (None, Some(deadline)) if round > 0 && Instant::now() >= deadline => break,
+            _ => (),
+        }
+        for (i, &dir) in legal.iter().enumerate() {
+            let mut gm = base.fork(GameRng::from_seed(rng.next_u64()));
+            gm.keep_playing();
+            gm.move_to(dir);
+            let seed = rng.next_u64();
+            let score = match playout {
+                Playout::Random => GameRunner::new(gm, RandomPlayer::new(seed)).run().score,
+                Playout::Greedy => GameRunner::new(gm, GreedyPlayer).run().score,
+            };
+            totals[i].0 += score as f64;
+            totals[i].1 += 1;
+        }
+        round += 1;
+    }
+    totals
+}
+
+impl Player for RolloutPlayer {
+    fn choose(&mut self, view: &GameView) -> Option<Direction> {
+        let legal = view.legal_moves();
+        if legal.len() == 1 {
+            return Some(legal[0]);
+        }
+        self.evaluate(view).into_iter()
+            .fold(None, |best: Option<(Direction, f64)>, (dir, score)| match best {
+                Some((_, b)) if b >= score => best,
+                _ => Some((dir, score)),
+            })
+            .map(|(dir, _)| dir)
+    }
+}
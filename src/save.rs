@@ -0,0 +1,186 @@
+//! Saving and resuming games.
+//!
+//! A save file is JSON: `{"version": 2, "game": {...}}` where `game` is the
+//! serialized `GameManager` without its undo history. Version 1 files, from
+//! before rectangular boards, are still read.
+
+use std::error::Error;
+use std::fmt;
+use std::fs::File;
+use std::io;
+use std::path::Path;
+
+use serde_json::{self, Value};
+
+use game::{GameManager, Grid, MIN_SIZE, MAX_SIZE};
+
+/// Current save file format.
+pub const SAVE_VERSION: u32 = 2;
+
+#[derive(Serialize)]
+struct SaveFileRef<'a> {
+    version: u32,
+    game: &'a GameManager
+}
+
+/// Why a game could not be saved or loaded.
+#[derive(Debug)]
+pub enum SaveError {
+    Io(io::Error),
+    Format(serde_json::Error),
+    /// The file was written by an incompatible version.
+    Version(u32),
+    /// The board is `width` x `height`, too small or too big.
+    BoardSize(usize, usize),
+    /// The grid is not `width` x `height`.
+    GridSize(usize, usize),
+    /// A tile value is not a power of two.
+    TileValue((usize, usize), isize),
+    /// A tile's own coordinates disagree with the cell holding it.
+    TilePosition((usize, usize), (usize, usize)),
+    /// The target tile is not a power of two of at least 4.
+    TargetTile(isize),
+    /// The spawn rule makes no sense.
+    SpawnRule(String),
+    /// The random source is stuck, see `GameRng::is_valid`.
+    Rng,
+}
+
+impl fmt::Display for SaveError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SaveError::Io(ref e) => write!(f, "{}", e),
+            SaveError::Format(ref e) => write!(f, "malformed save file: {}", e),
+            SaveError::Version(v) =>
+                write!(f, "unsupported save version {} (expected {})", v, SAVE_VERSION),
+            SaveError::BoardSize(width, height) =>
+                write!(f, "a {}x{} board is not {} to {} cells a side", width, height, MIN_SIZE, MAX_SIZE),
+            SaveError::GridSize(width, height) => write!(f, "grid is not {}x{}", width, height),
+            SaveError::TileValue(pos, value) =>
+                write!(f, "tile at {:?} has invalid value {}", pos, value),
+            SaveError::TilePosition(pos, tile_pos) =>
+                write!(f, "tile at {:?} claims to be at {:?}", pos, tile_pos),
+            SaveError::TargetTile(value) => write!(f, "target tile {} is not a power of two above 2", value),
+            SaveError::SpawnRule(ref msg) => write!(f, "invalid spawn rule: {}", msg),
+            SaveError::Rng => write!(f, "random state is 0"),
+        }
+    }
+}
+
+impl Error for SaveError {
+    fn description(&self) -> &str {
+        match *self {
+            SaveError::Io(_) => "i/o error",
+            SaveError::Format(_) => "malformed save file",
+            SaveError::Version(_) => "unsupported save version",
+            SaveError::BoardSize(..) => "board size out of range",
+            SaveError::GridSize(..) => "wrong grid size",
+            SaveError::TileValue(..) => "invalid tile value",
+            SaveError::TilePosition(..) => "tile position mismatch",
+            SaveError::TargetTile(_) => "invalid target tile",
+            SaveError::SpawnRule(_) => "invalid spawn rule",
+            SaveError::Rng => "invalid random state",
+        }
+    }
+}
+
+impl From<io::Error> for SaveError {
+    fn from(e: io::Error) -> SaveError {
+        SaveError::Io(e)
+    }
+}
+
+impl From<serde_json::Error> for SaveError {
+    fn from(e: serde_json::Error) -> SaveError {
+        SaveError::Format(e)
+    }
+}
+
+impl GameManager {
+    /// Writes the game to `path`, the undo history is not kept.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), SaveError> {
+        let file = try!(File::create(path));
+        try!(serde_json::to_writer(file, &SaveFileRef { version: SAVE_VERSION, game: self }));
+        Ok(())
+    }
+
+    /// Reads back a game written by `save()`, checking the board is sound.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<GameManager, SaveError> {
+        let file = try!(File::open(path));
+        let mut save: Value = try!(serde_json::from_reader(file));
+        let version = save.get("version").and_then(Value::as_u64).unwrap_or(0) as u32;
+        match version {
+            1 => upgrade_v1(&mut save["game"]),
+            SAVE_VERSION => (),
+            _ => return Err(SaveError::Version(version)),
+        }
+        let gm: GameManager = try!(serde_json::from_value(save["game"].take()));
+        // older files are only trusted once upgraded, like current ones
+        try!(gm.validate());
+        Ok(gm)
+    }
+
+    /// Checks what a file may get wrong: the board size, the tiles, the
+    /// target tile, the spawn rule and the random state.
+    pub fn validate(&self) -> Result<(), SaveError> {
+        try!(validate_size(self.width, self.height));
+        if self.grid.width != self.width || self.grid.height != self.height {
+            return Err(SaveError::GridSize(self.width, self.height));
+        }
+        try!(validate_grid(&self.grid));
+        if self.target_tile < 4 || self.target_tile & (self.target_tile - 1) != 0 {
+            return Err(SaveError::TargetTile(self.target_tile));
+        }
+        try!(self.spawn_rule.validate());
+        if !self.rng().is_valid() {
+            return Err(SaveError::Rng);
+        }
+        Ok(())
+    }
+}
+
+/// Checks a `width` x `height` board has `MIN_SIZE` to `MAX_SIZE` cells a
+/// side, before anything that big is built.
+pub fn validate_size(width: usize, height: usize) -> Result<(), SaveError> {
+    let side = |n: usize| (MIN_SIZE..=MAX_SIZE).contains(&n);
+    if !side(width) || !side(height) {
+        return Err(SaveError::BoardSize(width, height));
+    }
+    Ok(())
+}
+
+// v1 boards were square with a single `size`.
+fn upgrade_v1(game: &mut Value) {
+    split_size(game);
+    if let Some(grid) = game.get_mut("grid") {
+        split_size(grid);
+    }
+}
+
+fn split_size(value: &mut Value) {
+    if let Some(obj) = value.as_object_mut() {
+        if let Some(size) = obj.remove("size") {
+            obj.insert("width".to_string(), size.clone());
+            obj.insert("height".to_string(), size);
+        }
+    }
+}
+
+fn validate_grid(grid: &Grid) -> Result<(), SaveError> {
+    if grid.cells.len() != grid.width || grid.cells.iter().any(|col| col.len() != grid.height) {
+        return Err(SaveError::GridSize(grid.width, grid.height));
+    }
+    for (x, col) in grid.cells.iter().enumerate() {
+        for (y, cell) in col.iter().enumerate() {
+            if let Some(ref tile) = *cell {
+                if tile.value < 2 || tile.value & (tile.value - 1) != 0 {
+                    return Err(SaveError::TileValue((x, y), tile.value));
+                }
+                if tile.pos() != (x, y) {
+                    return Err(SaveError::TilePosition((x, y), tile.pos()));
+                }
+            }
+        }
+    }
+    Ok(())
+}
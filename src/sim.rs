@@ -0,0 +1,201 @@
+//! Headless batch games, to compare strategies and time the engine.
+//!
+//! Every game is driven by a `GameRunner` until no move is left, playing on
+//! past the target tile, with the batch's `SpawnRule`. Game `i` of a batch is seeded
+//! with `seed + i`, so a batch gives the same results whatever the number of
+//! threads running it, unless expectimax games share a transposition table:
+//! what they find there depends on which game searched a board first.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use rand::SeedableRng;
+
+use ai::Expectimax;
+use game::GameManager;
+use ntuple::NTupleNetwork;
+use player::{Player, GameRunner, RandomPlayer, GreedyPlayer, CornerPlayer, TrainedPlayer};
+use rng::GameRng;
+use rollout::{RolloutPlayer, Playout, Budget};
+use spawn::SpawnRule;
+use transposition::TranspositionTable;
+
+/// The players a batch can be run with, see `player`.
+#[derive(Debug, Clone)]
+pub enum Strategy {
+    Random,
+    Greedy,
+    Corner,
+    /// Games share the table if any.
+    Expectimax(Option<Arc<TranspositionTable>>),
+    /// Playouts of every move, on this many threads of the game's own, 1
+    /// playing them on the game's thread.
+    Rollout(Playout, Budget, usize),
+    /// Shares the network between all games.
+    Trained(Arc<NTupleNetwork>),
+}
+
+pub static STRATEGY_NAMES: &'static [&'static str] =
+    &["random", "greedy", "corner", "expectimax", "rollout", "trained"];
+
+impl FromStr for Strategy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Strategy, String> {
+        match s {
+            "random" => Ok(Strategy::Random),
+            "greedy" => Ok(Strategy::Greedy),
+            "corner" => Ok(Strategy::Corner),
+            "expectimax" => Ok(Strategy::Expectimax(None)),
+            "rollout" => Ok(Strategy::Rollout(Playout::Random, Budget::Rollouts(100), 1)),
+            "trained" => Err("the trained player needs a network, see `Strategy::Trained`".to_string()),
+            _ => Err(format!("unknown player `{}`", s)),
+        }
+    }
+}
+
+impl fmt::Display for Strategy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match *self {
+            Strategy::Random => "random",
+            Strategy::Greedy => "greedy",
+            Strategy::Corner => "corner",
+            Strategy::Expectimax(_) => "expectimax",
+            Strategy::Rollout(..) => "rollout",
+            Strategy::Trained(_) => "trained",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl Strategy {
+    /// Player for one game, `seed` feeds the random and rollout players.
+    pub fn player(&self, seed: u64) -> Box<Player + Send> {
+        match *self {
+            Strategy::Random => Box::new(RandomPlayer::new(seed)),
+            Strategy::Greedy => Box::new(GreedyPlayer),
+            Strategy::Corner => Box::new(CornerPlayer),
+            Strategy::Expectimax(ref cache) => Box::new(Expectimax { cache: cache.clone(), ..Expectimax::default() }),
+            Strategy::Rollout(playout, budget, threads) => {
+                let mut player = RolloutPlayer::new(seed);
+                player.playout = playout;
+                player.budget = budget;
+                player.threads = threads;
+                Box::new(player)
+            }
+            Strategy::Trained(ref network) => Box::new(TrainedPlayer::new(network.clone())),
+        }
+    }
+}
+
+/// Outcome of one simulated game.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct GameResult {
+    pub seed: u64,
+    pub score: usize,
+    pub max_tile: isize,
+    pub moves: usize,
+    /// Whether the target tile was reached.
+    pub won: bool
+}
+
+/// Plays a whole `width` x `height` game seeded with `seed`.
+pub fn play_game(strategy: &Strategy, rule: &SpawnRule, width: usize, height: usize, seed: u64) -> GameResult {
+    let mut gm = GameManager::with_dimensions(width, height, GameRng::from_seed(seed));
+    gm.spawn_rule = rule.clone();
+    // kept for undo, useless here
+    gm.history_depth = 0;
+    gm.setup();
+    gm.keep_playing();
+    // a stream of its own, so random players do not shift the spawns
+    let mut runner = GameRunner::new(gm, strategy.player(!seed));
+    let gm = runner.run();
+    GameResult { seed: seed, score: gm.score, max_tile: gm.grid.max_tile(), moves: gm.moves, won: gm.won }
+}
+
+/// Plays `games` games on `threads` worker threads, results come in game
+/// order.
+pub fn simulate(strategy: &Strategy, rule: &SpawnRule, width: usize, height: usize, seed: u64,
+                games: usize, threads: usize) -> Vec<GameResult> {
+    let rule = Arc::new(rule.clone());
+    let next = Arc::new(AtomicUsize::new(0));
+    let results = Arc::new(Mutex::new(vec![None; games]));
+    let workers = (0..threads.max(1)).map(|_| {
+        let next = next.clone();
+        let results = results.clone();
+        let rule = rule.clone();
+        let strategy = strategy.clone();
+        thread::spawn(move || loop {
+            let i = next.fetch_add(1, Ordering::SeqCst);
+            if i >= games {
+                break;
+            }
+            let result = play_game(&strategy, &rule, width, height, seed.wrapping_add(i as u64));
+            results.lock().unwrap()[i] = Some(result);
+        })
+    }).collect::<Vec<_>>();
+    for worker in workers {
+        worker.join().unwrap();
+    }
+    let results = results.lock().unwrap();
+    results.iter().map(|r| r.unwrap()).collect()
+}
+
+/// Distributions over a batch of games.
+#[derive(Debug, Clone, Serialize)]
+pub struct Summary {
+    pub games: usize,
+    pub wins: usize,
+    pub mean_score: f64,
+    /// Score at the 0th, 10th, 25th, 50th, 75th, 90th and 100th percentiles.
+    pub score_percentiles: BTreeMap<u8, usize>,
+    /// Number of games per highest tile reached.
+    pub max_tiles: BTreeMap<isize, usize>,
+    pub mean_moves: f64,
+    pub min_moves: usize,
+    pub max_moves: usize,
+    pub games_per_sec: f64
+}
+
+pub static PERCENTILES: &'static [u8] = &[0, 10, 25, 50, 75, 90, 100];
+
+impl Summary {
+    /// `elapsed` is how long the batch took.
+    pub fn new(results: &[GameResult], elapsed: Duration) -> Summary {
+        let n = results.len();
+        let mut scores = results.iter().map(|r| r.score).collect::<Vec<_>>();
+        scores.sort();
+        let percentile = |p: u8| if n == 0 { 0 } else { scores[(n - 1) * p as usize / 100] };
+        let mut max_tiles = BTreeMap::new();
+        for r in results {
+            *max_tiles.entry(r.max_tile).or_insert(0) += 1;
+        }
+        let mean = |total: usize| if n == 0 { 0.0 } else { total as f64 / n as f64 };
+        let secs = elapsed.as_secs() as f64 + elapsed.subsec_nanos() as f64 / 1e9;
+        Summary {
+            games: n,
+            wins: results.iter().filter(|r| r.won).count(),
+            mean_score: mean(scores.iter().sum()),
+            score_percentiles: PERCENTILES.iter().map(|&p| (p, percentile(p))).collect(),
+            max_tiles: max_tiles,
+            mean_moves: mean(results.iter().map(|r| r.moves).sum()),
+            min_moves: results.iter().map(|r| r.moves).min().unwrap_or(0),
+            max_moves: results.iter().map(|r| r.moves).max().unwrap_or(0),
+            games_per_sec: if secs > 0.0 { n as f64 / secs } else { 0.0 },
+        }
+    }
+}
+
+/// Runs a batch like `simulate()` and sums it up.
+pub fn run_batch(strategy: &Strategy, rule: &SpawnRule, width: usize, height: usize, seed: u64,
+                 games: usize, threads: usize) -> (Vec<GameResult>, Summary) {
+    let start = Instant::now();
+    let results = simulate(strategy, rule, width, height, seed, games, threads);
+    let summary = Summary::new(&results, start.elapsed());
+    (results, summary)
+}
@@ -0,0 +1,250 @@
+//! Which tiles appear after a move, and where.
+//!
+//! The classic rules drop one tile after every move, a 2 (weight 9) or a 4
+//! (weight 1) on a random empty cell, and start with two tiles. A
+//! `SpawnRule` changes any of this. It reads from JSON, every field being
+//! optional:
+//!
+//! ```json
+//! {"values": [[2, 9], [4, 1]], "per_move": 1, "start_tiles": 2, "policy": "uniform"}
+//! {"policy": {"evil": {"depth": 2, "chance": 0.5}}}
+//! {"policy": {"fixed": [[0, 0, 2], [3, 3, 2], [1, 2, 4]]}}
+//! ```
+//!
+//! Start tiles are spawns too, the last example starts with the first two
+//! tiles of its list.
+
+use std::fmt;
+use std::fs::File;
+use std::path::Path;
+use std::str::FromStr;
+
+use rand::Rng;
+use serde_json;
+
+use ai::Adversary;
+use game::{Grid, Tile, TileSpec, MAX_SIZE};
+use save::SaveError;
+use stats;
+
+/// How the cell and value of a new tile are chosen.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SpawnPolicy {
+    /// A random empty cell, the value drawn from the weights.
+    Uniform,
+    /// The cell and value leaving the player worst off, looking `depth`
+    /// moves ahead, for a `chance` of the spawns, the others being
+    /// `Uniform`. Weights only count for those, the evil ones pick among
+    /// the values. Reads the `adversarial` policy of older files, always
+    /// evil.
+    #[serde(alias = "adversarial")]
+    Evil {
+        #[serde(default = "default_depth")]
+        depth: usize,
+        #[serde(default = "default_chance")]
+        chance: f64
+    },
+    /// Tiles dropped in this order, for puzzles. Once the list is used up,
+    /// or when the cell is taken or off the board, spawns are `Uniform`.
+    Fixed(Vec<TileSpec>),
+}
+
+/// Most tiles a rule may drop after a move, the cells of the biggest board.
+pub const MAX_PER_MOVE: usize = MAX_SIZE * MAX_SIZE;
+
+/// Most moves the evil spawner may look ahead, every one more multiplies
+/// its search by the spawns and moves of a board.
+pub const MAX_EVIL_DEPTH: usize = 3;
+
+fn default_depth() -> usize {
+    1
+}
+
+fn default_chance() -> f64 {
+    1.0
+}
+
+/// Strength presets of the `Evil` policy.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Difficulty {
+    /// Each spawn is evil with a 50% chance.
+    Easy,
+    /// Every tile is evil, one move of lookahead.
+    Normal,
+    /// Every tile is evil, two moves of lookahead.
+    Hard,
+}
+
+pub static DIFFICULTIES: &'static [Difficulty] = &[Difficulty::Easy, Difficulty::Normal, Difficulty::Hard];
+
+pub static DIFFICULTY_NAMES: &'static [&'static str] = &["easy", "normal", "hard"];
+
+impl FromStr for Difficulty {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Difficulty, String> {
+        match s {
+            "easy" => Ok(Difficulty::Easy),
+            "normal" => Ok(Difficulty::Normal),
+            "hard" => Ok(Difficulty::Hard),
+            _ => Err(format!("unknown difficulty `{}`", s)),
+        }
+    }
+}
+
+impl fmt::Display for Difficulty {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match *self {
+            Difficulty::Easy => "easy",
+            Difficulty::Normal => "normal",
+            Difficulty::Hard => "hard",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl Difficulty {
+    pub fn policy(&self) -> SpawnPolicy {
+        match *self {
+            Difficulty::Easy => SpawnPolicy::Evil { depth: 1, chance: 0.5 },
+            Difficulty::Normal => SpawnPolicy::Evil { depth: 1, chance: 1.0 },
+            Difficulty::Hard => SpawnPolicy::Evil { depth: 2, chance: 1.0 },
+        }
+    }
+}
+
+/// Spawn configuration of a game.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SpawnRule {
+    /// Values a new tile can take, with their relative weights.
+    pub values: Vec<(isize, u32)>,
+    /// Tiles dropped after every move.
+    pub per_move: usize,
+    /// Tiles on the board when a game starts.
+    pub start_tiles: usize,
+    pub policy: SpawnPolicy
+}
+
+impl Default for SpawnRule {
+    fn default() -> SpawnRule {
+        SpawnRule { values: vec![(2, 9), (4, 1)], per_move: 1, start_tiles: 2, policy: SpawnPolicy::Uniform }
+    }
+}
+
+impl SpawnRule {
+    /// Classic values and counts, spawns by the evil policy at this
+    /// difficulty.
+    pub fn evil(difficulty: Difficulty) -> SpawnRule {
+        SpawnRule { policy: difficulty.policy(), ..SpawnRule::default() }
+    }
+
+    /// Reads a rule from a JSON file and checks it.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<SpawnRule, SaveError> {
+        let rule: SpawnRule = try!(serde_json::from_reader(try!(File::open(path))));
+        try!(rule.validate());
+        Ok(rule)
+    }
+
+    /// Checks values are powers of two with some weight, that fixed
+    /// tiles are too, that games do not start on an empty board, that
+    /// moves drop between 1 and `MAX_PER_MOVE` tiles, that the evil spawner
+    /// looks at most `MAX_EVIL_DEPTH` moves ahead and that its chance is a
+    /// probability.
+    pub fn validate(&self) -> Result<(), SaveError> {
+        if self.start_tiles == 0 {
+            return Err(SaveError::SpawnRule("a game needs at least one start tile".to_string()));
+        }
+        if self.per_move == 0 || self.per_move > MAX_PER_MOVE {
+            return Err(SaveError::SpawnRule(format!("{} tiles per move is not between 1 and {}",
+                                                    self.per_move, MAX_PER_MOVE)));
+        }
+        let power_of_two = |v: isize| v >= 2 && v & (v - 1) == 0;
+        if let Some(&(v, _)) = self.values.iter().find(|&&(v, _)| !power_of_two(v)) {
+            return Err(SaveError::SpawnRule(format!("spawn value {} is not a power of two", v)));
+        }
+        if self.values.iter().all(|&(_, w)| w == 0) {
+            return Err(SaveError::SpawnRule("no spawn value has a weight".to_string()));
+        }
+        if self.values.iter().try_fold(0u32, |total, &(_, w)| total.checked_add(w)).is_none() {
+            return Err(SaveError::SpawnRule(format!("spawn weights add up to more than {}", u32::MAX)));
+        }
+        match self.policy {
+            SpawnPolicy::Fixed(ref tiles) => {
+                if let Some(&tile) = tiles.iter().find(|&&(_, _, v)| !power_of_two(v)) {
+                    return Err(SaveError::SpawnRule(format!("fixed tile {:?} is not a power of two", tile)));
+                }
+            }
+            SpawnPolicy::Evil { depth, .. } if depth > MAX_EVIL_DEPTH => {
+                return Err(SaveError::SpawnRule(format!("evil depth {} is more than {}", depth, MAX_EVIL_DEPTH)));
+            }
+            SpawnPolicy::Evil { chance, .. } if !(0.0..=1.0).contains(&chance) => {
+                return Err(SaveError::SpawnRule(format!("evil chance {} is not between 0 and 1", chance)));
+            }
+            _ => (),
+        }
+        Ok(())
+    }
+
+    /// Name under which scores are kept, `stats::CLASSIC` for the
+    /// default rule.
+    pub fn variant(&self) -> String {
+        if *self == SpawnRule::default() {
+            return stats::CLASSIC.to_string();
+        }
+        match self.policy {
+            SpawnPolicy::Evil { depth, chance } => {
+                match DIFFICULTIES.iter().find(|&&d| *self == SpawnRule::evil(d)) {
+                    Some(d) => format!("evil-{}", d),
+                    None => format!("evil-{}-{}", depth, (chance * 100.0).round()),
+                }
+            }
+            SpawnPolicy::Fixed(_) => "puzzle".to_string(),
+            SpawnPolicy::Uniform => "custom".to_string(),
+        }
+    }
+
+    fn random_value<R: Rng>(&self, rng: &mut R) -> isize {
+        // no overflow even for a rule never validated
+        let total = self.values.iter().map(|&(_, w)| w as u64).sum::<u64>();
+        let mut r = rng.gen_range(0, total);
+        for &(value, weight) in &self.values {
+            if r < weight as u64 {
+                return value;
+            }
+            r -= weight as u64;
+        }
+        unreachable!()
+    }
+
+    fn uniform<R: Rng>(&self, grid: &Grid, rng: &mut R) -> Option<Tile> {
+        if !grid.cells_available() {
+            return None;
+        }
+        let value = self.random_value(rng);
+        grid.random_available_cell(rng).map(|pos| Tile::new(pos, value))
+    }
+
+    /// Next tile to drop on `grid`, `count` being the number of tiles
+    /// spawned so far in the game. `None` if the grid is full.
+    pub fn spawn<R: Rng>(&self, grid: &Grid, rng: &mut R, count: usize) -> Option<Tile> {
+        match self.policy {
+            SpawnPolicy::Uniform => self.uniform(grid, rng),
+            // a spawner evil every time draws nothing from the rng
+            SpawnPolicy::Evil { chance, .. } if chance < 1.0 && rng.gen::<f64>() >= chance => {
+                self.uniform(grid, rng)
+            }
+            SpawnPolicy::Evil { depth, .. } => {
+                let values = self.values.iter().filter(|&&(_, w)| w > 0).map(|&(v, _)| v).collect::<Vec<_>>();
+                Adversary { depth: depth, ..Adversary::default() }.worst_spawn(grid, &values)
+            }
+            SpawnPolicy::Fixed(ref tiles) => match tiles.get(count) {
+                Some(&(x, y, value)) if grid.within_bounds((x, y)) && grid.cell_available((x, y)) => {
+                    Some(Tile::new((x, y), value))
+                }
+                _ => self.uniform(grid, rng),
+            },
+        }
+    }
+}
@@ -0,0 +1,102 @@
+//! High scores and statistics kept across games.
+//!
+//! Finished games are tallied per board size and variant, a variant being
+//! any change of the rules (the classic game is `CLASSIC`). The store is a
+//! small JSON file, see `Stats::load()` and `Stats::save()`.
+
+use std::fs::File;
+use std::io;
+use std::path::Path;
+
+use serde_json;
+
+use game::GameManager;
+use save::SaveError;
+
+/// Variant name of the standard rules.
+pub const CLASSIC: &'static str = "classic";
+
+/// Tally of the finished games played on one board size and variant.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Record {
+    pub width: usize,
+    pub height: usize,
+    pub variant: String,
+    pub games: usize,
+    /// Games where the target tile was reached.
+    pub wins: usize,
+    pub best_score: usize,
+    pub highest_tile: isize,
+    pub total_moves: usize,
+    pub total_score: usize
+}
+
+impl Record {
+    fn new(width: usize, height: usize, variant: &str) -> Record {
+        Record { width: width, height: height, variant: variant.to_string(), games: 0, wins: 0,
+                 best_score: 0, highest_tile: 0, total_moves: 0, total_score: 0 }
+    }
+
+    /// Mean score of the finished games, 0 before any.
+    pub fn average_score(&self) -> f64 {
+        if self.games == 0 { 0.0 } else { self.total_score as f64 / self.games as f64 }
+    }
+}
+
+/// Every `Record`, in the order board sizes and variants were first played.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Stats {
+    pub records: Vec<Record>
+}
+
+impl Stats {
+    /// Reads the store at `path`, a missing file is an empty store.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Stats, SaveError> {
+        match File::open(path) {
+            Ok(file) => Ok(try!(serde_json::from_reader(file))),
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => Ok(Stats::default()),
+            Err(e) => Err(SaveError::Io(e)),
+        }
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), SaveError> {
+        let file = try!(File::create(path));
+        try!(serde_json::to_writer_pretty(file, self));
+        Ok(())
+    }
+
+    /// Record of a board size and variant, if a game was ever finished on it.
+    pub fn record(&self, width: usize, height: usize, variant: &str) -> Option<&Record> {
+        self.records.iter().find(|r| r.width == width && r.height == height && r.variant == variant)
+    }
+
+    /// Best score on `gm`'s board size, 0 if none yet.
+    pub fn best_score(&self, gm: &GameManager, variant: &str) -> usize {
+        self.record(gm.width, gm.height, variant).map_or(0, |r| r.best_score)
+    }
+
+    /// Adds a finished game to the tally.
+    pub fn add_game(&mut self, gm: &GameManager, variant: &str) {
+        let idx = match self.records.iter()
+            .position(|r| r.width == gm.width && r.height == gm.height && r.variant == variant) {
+            Some(idx) => idx,
+            None => {
+                self.records.push(Record::new(gm.width, gm.height, variant));
+                self.records.len() - 1
+            }
+        };
+        let record = &mut self.records[idx];
+        record.games += 1;
+        if gm.won {
+            record.wins += 1;
+        }
+        if gm.score > record.best_score {
+            record.best_score = gm.score;
+        }
+        if gm.grid.max_tile() > record.highest_tile {
+            record.highest_tile = gm.grid.max_tile();
+        }
+        record.total_moves += gm.moves;
+        record.total_score += gm.score;
+    }
+}
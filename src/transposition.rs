@@ -0,0 +1,121 @@
+//! Bounded cache of search results, shared between searchers and threads.
+//!
+//! Entries are keyed by a board's Zobrist hash (`Grid::zobrist()`) and the
+//! depth it was searched to. The table has a fixed number of slots, a new
+//! entry takes over its slot whatever was there. Slots are spread over
+//! shards with a lock each, so that threads seldom wait on each other.
+//!
+//! Values only make sense to searchers scoring boards alike: share a table
+//! between searchers with the same heuristic.
+
+use std::fmt;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+const SHARDS: usize = 64;
+
+#[derive(Debug, Clone, Copy)]
+struct Entry {
+    hash: u64,
+    depth: u32,
+    value: f64
+}
+
+/// Lookup counts of a table.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct CacheStats {
+    pub hits: usize,
+    pub misses: usize,
+    /// Slots in use.
+    pub entries: usize,
+    pub capacity: usize
+}
+
+impl CacheStats {
+    /// Share of lookups that hit, 0 before any.
+    pub fn hit_rate(&self) -> f64 {
+        let lookups = self.hits + self.misses;
+        if lookups == 0 { 0.0 } else { self.hits as f64 / lookups as f64 }
+    }
+}
+
+pub struct TranspositionTable {
+    shards: Vec<Mutex<Vec<Option<Entry>>>>,
+    hits: AtomicUsize,
+    misses: AtomicUsize
+}
+
+impl fmt::Debug for TranspositionTable {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("TranspositionTable").field("stats", &self.stats()).finish()
+    }
+}
+
+impl TranspositionTable {
+    /// A table of at most `capacity` entries, at least one.
+    pub fn new(capacity: usize) -> TranspositionTable {
+        let capacity = capacity.max(1);
+        let shards = SHARDS.min(capacity);
+        TranspositionTable {
+            shards: (0..shards).map(|i| {
+                // the first shards take the remainder
+                let size = capacity / shards + if i < capacity % shards { 1 } else { 0 };
+                Mutex::new(vec![None; size])
+            }).collect(),
+            hits: AtomicUsize::new(0),
+            misses: AtomicUsize::new(0),
+        }
+    }
+
+    // shard and slot of a key
+    fn slot(&self, hash: u64, depth: u32) -> (usize, u64) {
+        let key = hash ^ (depth as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15);
+        ((key % self.shards.len() as u64) as usize, key / self.shards.len() as u64)
+    }
+
+    /// Value stored for this board and depth, if still there.
+    pub fn get(&self, hash: u64, depth: u32) -> Option<f64> {
+        let (shard, slot) = self.slot(hash, depth);
+        let shard = self.shards[shard].lock().unwrap();
+        let found = match shard[(slot % shard.len() as u64) as usize] {
+            Some(entry) if entry.hash == hash && entry.depth == depth => Some(entry.value),
+            _ => None,
+        };
+        let counter = if found.is_some() { &self.hits } else { &self.misses };
+        counter.fetch_add(1, Ordering::Relaxed);
+        found
+    }
+
+    pub fn insert(&self, hash: u64, depth: u32, value: f64) {
+        let (shard, slot) = self.slot(hash, depth);
+        let mut shard = self.shards[shard].lock().unwrap();
+        let len = shard.len() as u64;
+        shard[(slot % len) as usize] = Some(Entry { hash: hash, depth: depth, value: value });
+    }
+
+    /// Forgets every entry and resets the counts.
+    pub fn clear(&self) {
+        for shard in &self.shards {
+            for entry in shard.lock().unwrap().iter_mut() {
+                *entry = None;
+            }
+        }
+        self.hits.store(0, Ordering::Relaxed);
+        self.misses.store(0, Ordering::Relaxed);
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        let (mut entries, mut capacity) = (0, 0);
+        for shard in &self.shards {
+            let shard = shard.lock().unwrap();
+            entries += shard.iter().filter(|e| e.is_some()).count();
+            capacity += shard.len();
+        }
+        CacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            entries: entries,
+            capacity: capacity,
+        }
+    }
+}
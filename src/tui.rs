@@ -0,0 +1,235 @@
+use std::cmp;
+use std::io::{self, Write};
+use std::path::Path;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use termion;
+use termion::color::{self, Rgb};
+use termion::event::Key;
+use termion::input::TermRead;
+use termion::raw::IntoRawMode;
+use termion::screen::AlternateScreen;
+
+use game2048::game;
+use game2048::Direction;
+use game2048::{Playback, Recorder, GameRunner, HumanPlayer};
+use game2048::replay::{MIN_INTERVAL_MS, MAX_INTERVAL_MS};
+
+// colors of the original game
+static BG_COLOR: Rgb = Rgb(0xbb, 0xad, 0xa0);
+static FG_COLOR: Rgb = Rgb(0x77, 0x6e, 0x65);
+static LIGHT_FG_COLOR: Rgb = Rgb(0xf9, 0xf6, 0xf2);
+static CELL_COLORS: &'static [Rgb] = &[
+    Rgb(0xcd, 0xc1, 0xb4), Rgb(0xee, 0xe4, 0xda), Rgb(0xed, 0xe0, 0xc8),
+    Rgb(0xf2, 0xb1, 0x79), Rgb(0xf5, 0x95, 0x64), Rgb(0xf6, 0x7c, 0x5f),
+    Rgb(0xf6, 0x5e, 0x3b), Rgb(0xed, 0xcf, 0x72), Rgb(0xed, 0xcc, 0x61),
+    Rgb(0xed, 0xc8, 0x50), Rgb(0xed, 0xc5, 0x3f), Rgb(0xed, 0xc2, 0x2e), ];
+static SUPER_CELL_COLOR: Rgb = Rgb(0x3c, 0x3a, 0x32);
+
+static CELL_WIDTH: usize = 6;
+
+static PLAY_HELP: &'static str = "arrows/wasd/hjkl: move  u: undo  r: redo  q: quit";
+static REPLAY_HELP: &'static str =
+    "left/right: step  space: play/pause  +/-: speed  home/end: first/last  NUM enter: go to move  q: quit";
+
+// Layout, one cell per (x, y) with x the column:
+//
+// +------+------+------+------+
+// |    2 |      |      |      |
+// +------+------+------+------+
+// |      |    4 |      |      |
+// +------+------+------+------+
+fn draw_game<W: Write>(out: &mut W, gm: &game::GameManager, status: &str, msg: &str,
+                       help: &str) -> io::Result<()> {
+    let border = format!("+{}", format!("{}+", "-".repeat(CELL_WIDTH)).repeat(gm.width));
+
+    try!(write!(out, "{}{}", termion::clear::All, termion::cursor::Goto(1, 1)));
+    try!(write!(out, "Rust - 2048\r\n\r\n"));
+    try!(write!(out, "{}\r\n\r\n", status));
+    for y in 0..gm.height {
+        try!(write!(out, "{}{}{}\r\n", color::Fg(BG_COLOR), border, color::Fg(color::Reset)));
+        for x in 0..gm.width {
+            try!(write!(out, "{}|{}", color::Fg(BG_COLOR), color::Fg(color::Reset)));
+            match gm.grid.cells[x][y] {
+                Some(ref tile) => {
+                    let c = (tile.value as f64).log2() as usize;
+                    let bg = CELL_COLORS.get(c).map(|&co| co).unwrap_or(SUPER_CELL_COLOR);
+                    let fg = if c < 3 { FG_COLOR } else { LIGHT_FG_COLOR };
+                    try!(write!(out, "{}{}{:>w$} {}{}", color::Bg(bg), color::Fg(fg), tile.value,
+                                color::Fg(color::Reset), color::Bg(color::Reset), w = CELL_WIDTH - 1));
+                }
+                None => {
+                    try!(write!(out, "{}{}{}", color::Bg(CELL_COLORS[0]), " ".repeat(CELL_WIDTH),
+                                color::Bg(color::Reset)));
+                }
+            }
+        }
+        try!(write!(out, "{}|{}\r\n", color::Fg(BG_COLOR), color::Fg(color::Reset)));
+    }
+    try!(write!(out, "{}{}{}\r\n\r\n", color::Fg(BG_COLOR), border, color::Fg(color::Reset)));
+    try!(write!(out, "{}\r\n", msg));
+    try!(write!(out, "{}\r\n", help));
+    out.flush()
+}
+
+/// `data` is where the game is saved and scores are kept, `record` where
+/// to write a replay of the game if anywhere.
+pub fn run(gm: game::GameManager, data: &Path, record: Option<&Path>) -> io::Result<()> {
+    let stdin = io::stdin();
+    let stdout = try!(io::stdout().into_raw_mode());
+    let mut out = AlternateScreen::from(stdout);
+    try!(write!(out, "{}", termion::cursor::Hide));
+
+    let mut runner = GameRunner::new(gm, HumanPlayer::new());
+    let mut playing = runner.gm.playing;
+    let mut stats = ::load_stats(data);
+    let mut recorder = Recorder::new(&runner.gm);
+    let mut keys = stdin.keys();
+
+    loop {
+        let best = cmp::max(runner.gm.score, stats.best_score(&runner.gm, &runner.gm.spawn_rule.variant()));
+        let msg = if !playing {
+            "Press SPACE to start!".to_string()
+        } else if !runner.gm.moves_available() {
+            format!("Score: {}! Max Cell: {}  (SPACE: new game)", runner.gm.score, runner.gm.grid.max_tile())
+        } else if runner.gm.is_terminated() {
+            "You win! Continue? (y/n)".to_string()
+        } else {
+            String::new()
+        };
+        let status = format!("score: {:<8} best: {}", runner.gm.score, best);
+        try!(draw_game(&mut out, &runner.gm, &status, &msg, PLAY_HELP));
+
+        let key = match keys.next() {
+            Some(key) => try!(key),
+            None => break,
+        };
+        let moving = playing && !runner.gm.is_terminated();
+        let dir = match key {
+            Key::Left  | Key::Char('a') | Key::Char('h') => Some(Direction::Left),
+            Key::Right | Key::Char('d') | Key::Char('l') => Some(Direction::Right),
+            Key::Up    | Key::Char('w') | Key::Char('k') => Some(Direction::Up),
+            Key::Down  | Key::Char('s') | Key::Char('j') => Some(Direction::Down),
+            _ => None,
+        };
+        match (key, dir) {
+            (_, Some(dir)) if moving => {
+                runner.player.push(dir);
+                if let Some((dir, outcome)) = runner.step() {
+                    recorder.record(&runner.gm, dir, &outcome);
+                    if outcome.lost {
+                        ::record_game(&mut stats, &runner.gm, data);
+                        ::save_replay(&recorder, &runner.gm, record);
+                    }
+                }
+            }
+            (Key::Char('q'), _) | (Key::Esc, _) | (Key::Ctrl('c'), _) => break,
+            // a lost game is already recorded, no way back
+            (Key::Char('u'), _) if playing && runner.gm.moves_available() => {
+                runner.gm.undo();
+            }
+            (Key::Char('r'), _) if playing && runner.gm.moves_available() => {
+                runner.gm.redo();
+            }
+            (Key::Char('y'), _) if playing && runner.gm.won => runner.gm.keep_playing(),
+            (Key::Char('n'), _) if playing && runner.gm.is_terminated() && runner.gm.moves_available() => {
+                ::record_game(&mut stats, &runner.gm, data);
+                ::save_replay(&recorder, &runner.gm, record);
+                playing = false;
+            }
+            (Key::Char(' '), _) if !playing || !runner.gm.moves_available() => {
+                playing = true;
+                runner.gm.setup();
+                recorder = Recorder::new(&runner.gm);
+            }
+            _ => ()
+        }
+    }
+
+    try!(write!(out, "{}", termion::cursor::Show));
+    // a lost game is not worth resuming
+    ::autosave(&runner.gm, playing && runner.gm.moves_available(), data);
+    if playing && runner.gm.moves_available() {
+        ::save_replay(&recorder, &runner.gm, record);
+    }
+    out.flush()
+}
+
+/// Plays back a verified replay, paused on its first position.
+pub fn run_replay(playback: Playback) -> io::Result<()> {
+    let stdout = try!(io::stdout().into_raw_mode());
+    let mut out = AlternateScreen::from(stdout);
+    try!(write!(out, "{}", termion::cursor::Hide));
+
+    let mut playback = playback;
+    let mut keys = termion::async_stdin().keys();
+    let mut paused = true;
+    let mut interval_ms = 500;
+    let mut last_step = Instant::now();
+    let mut goto = String::new();
+    let mut dirty = true;
+
+    'main: loop {
+        if dirty {
+            let status = format!("move {}/{}  score: {}", playback.position(), playback.len(),
+                                 playback.game().score);
+            let msg = if !goto.is_empty() {
+                format!("go to move: {}", goto)
+            } else if paused {
+                "paused".to_string()
+            } else {
+                format!("playing, a move every {} ms", interval_ms)
+            };
+            try!(draw_game(&mut out, playback.game(), &status, &msg, REPLAY_HELP));
+            dirty = false;
+        }
+
+        while let Some(key) = keys.next() {
+            dirty = true;
+            match try!(key) {
+                Key::Char('q') | Key::Esc | Key::Ctrl('c') => break 'main,
+                Key::Right | Key::Char('l') => {
+                    playback.step_forward();
+                }
+                Key::Left | Key::Char('h') => {
+                    playback.step_back();
+                }
+                Key::Char(' ') => {
+                    paused = !paused;
+                    last_step = Instant::now();
+                }
+                Key::Char('+') | Key::Char('=') => interval_ms = cmp::max(interval_ms / 2, MIN_INTERVAL_MS),
+                Key::Char('-') => interval_ms = cmp::min(interval_ms * 2, MAX_INTERVAL_MS),
+                Key::Home => playback.seek(0),
+                Key::End => {
+                    let end = playback.len();
+                    playback.seek(end);
+                }
+                Key::Char(c) if c.is_digit(10) => goto.push(c),
+                Key::Backspace => {
+                    goto.pop();
+                }
+                Key::Char('\n') => {
+                    if let Ok(n) = goto.parse() {
+                        playback.seek(n);
+                    }
+                    goto.clear();
+                }
+                _ => ()
+            }
+        }
+
+        if !paused && last_step.elapsed() >= Duration::from_millis(interval_ms) {
+            if playback.step_forward().is_none() {
+                paused = true;
+            }
+            last_step = Instant::now();
+            dirty = true;
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+
+    try!(write!(out, "{}", termion::cursor::Show));
+    out.flush()
+}
@@ -1,4 +1,11 @@
+use std::cmp;
+use std::iter;
+use std::mem;
 use std::path::Path;
+use std::sync::Arc;
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::thread;
+use std::time::{Duration, Instant};
 use sdl2;
 use sdl2_gfx;
 use sdl2_ttf;
@@ -13,12 +20,24 @@ use sdl2::SdlResult;
 use sdl2::rwops;
 use sdl2::rect::Rect;
 use sdl2_ttf::RWopsFontExt;
-use game;
-use game::Direction;
+use game2048::game;
+use game2048::ai::{self, Expectimax, WeightedHeuristic};
+use game2048::TranspositionTable;
+use game2048::{Direction, GameManager, GameRng, MoveOutcome};
+use game2048::{Playback, Recorder, GameRunner, HumanPlayer, SpawnRule};
+use game2048::replay::{MIN_INTERVAL_MS, MAX_INTERVAL_MS};
+use game2048::spawn;
+use rand::SeedableRng;
 
 static SCREEN_WIDTH : i32 = 800;
 static SCREEN_HEIGHT : i32 = 600;
 
+// entries of the table hints and autoplay share
+const HINT_CACHE_SIZE: usize = 1 << 18;
+
+// value of every move, `None` for those that change nothing
+type MoveScores = Vec<(Direction, Option<f64>)>;
+
 // hadle the annoying Rect i32
 macro_rules! rect(
     ($x:expr, $y:expr, $w:expr, $h:expr) => (
@@ -37,6 +56,10 @@ static CELL_COLORS: &'static [Color] = &[
     Color::RGB(0xed, 0xcf, 0x72), Color::RGB(0xed, 0xcc, 0x61), Color::RGB(0xed, 0xc8, 0x50),
     Color::RGB(0xed, 0xc5, 0x3f), Color::RGB(0xed, 0xc2, 0x2e), Color::RGB(0x3c, 0x3a, 0x32), ];
 static SUPER_CELL_COLOR: Color = Color::RGB(0xcc, 0x33, 0xff);
+static HINT_COLOR: Color = Color::RGBA(0x77, 0x6e, 0x65, 220);
+static HINT_BEST_COLOR: Color = Color::RGBA(0xee, 0x33, 0x66, 220);
+static HINT_ILLEGAL_COLOR: Color = Color::RGBA(0xbb, 0xad, 0xa0, 160);
+static HINT_LABEL_COLOR: Color = Color::RGBA(0xee, 0xe4, 0xda, 220);
 
 // Font
 #[cfg(target_os="macos")]
@@ -45,60 +68,202 @@ static UNDER_MACOSX: bool = true;
 #[cfg(any(target_os="windows", target_os="linux", target_os="freebsd"))]
 static UNDER_MACOSX: bool = false;
 
+// Part of an animation spent sliding tiles, merges pop and new tiles grow
+// during the rest of it.
+static SLIDE_PART: f64 = 0.6;
+
+fn animation_progress(start: Option<Instant>, duration: Duration) -> f64 {
+    let to_ms = |d: Duration| d.as_secs() as f64 * 1000.0 + d.subsec_nanos() as f64 / 1e6;
+    match start {
+        Some(start) if to_ms(duration) > 0.0 => (to_ms(start.elapsed()) / to_ms(duration)).min(1.0),
+        _ => 1.0,
+    }
+}
+
+// Draws a tile centered on (cx, cy), `width` pixels wide.
 #[allow(unused_must_use)]
-fn draw_game(gm: &mut game::GameManager, ren: &mut render::Renderer, font: &sdl2_ttf::Font,
-                (x,y,w,h): (u32,u32,u32,u32)) -> SdlResult<()> {
-    assert_eq!(w, h);
+fn draw_tile(ren: &mut render::Renderer, font: &sdl2_ttf::Font, val: isize,
+             (cx, cy): (f64, f64), width: f64) {
+    if width < 1.0 {
+        return;
+    }
+    let c = if val == 0 {
+        0           // or will be +Infinity
+    } else {
+        (val as f64).log2() as usize
+    };
+    let color = CELL_COLORS.get(c).map(|&co| co).unwrap_or(SUPER_CELL_COLOR);
+    let half = width / 2.0;
+    ren.box_((cx - half) as i16, (cy - half) as i16, (cx + half) as i16, (cy + half) as i16, color);
+    if val != 0 {
+        let (tex, tw, th) = {
+            let wd = format!("{}", val);
+            let (w, h) = font.size(&wd[..]).ok().expect("size of str");
+            let text = font.render(&wd[..],
+                                   sdl2_ttf::RenderMode::Blended { foreground: FG_COLOR})
+                .ok().expect("renderred surface");
+            (ren.create_texture_from_surface(&text).ok().expect("create texture"), w, h)
+        };
+
+        let ratio = if tw as f64 > width {
+            width / tw as f64
+        } else if th as f64 > width {
+            width / th as f64
+        } else { 1.0 };
+
+        let tw = (tw as f64 * ratio) as u32;
+        let th = (th as f64 * ratio) as u32;
+
+        ren.copy(&tex, None, Rect::new(cx as i32 - tw as i32 / 2,
+                                       cy as i32 - th as i32 / 2,
+                                       tw, th).ok().expect("a rect"));
+    }
+}
+
+// `t` is the progress of the last move's animation, from 0 to 1. Cells stay
+// square, the board is centered in the given area, which it returns.
+fn draw_game(gm: &game::GameManager, ren: &mut render::Renderer, font: &sdl2_ttf::Font,
+             (x,y,w,h): (u32,u32,u32,u32), t: f64) -> SdlResult<(u32,u32,u32,u32)> {
     // BEST in 500x500
-    let size = gm.size as u32;
-    let container_padding: u32 = 50  / (size as u32 + 1);
-    let cell_width = (w - container_padding * (size + 1)) / size as u32 ;
+    let (cols, rows) = (gm.width as u32, gm.height as u32);
+    let container_padding: u32 = 50  / (cmp::max(cols, rows) + 1);
+    let cell_width = cmp::min((w - container_padding * (cols + 1)) / cols,
+                              (h - container_padding * (rows + 1)) / rows);
     assert!(cell_width > 40); // Min width
-    try!(ren.box_(x as i16, y as i16, (x+w) as i16, (y+h) as i16, CONTAINER_COLOR));
-    gm.grid.each_cell(|j, i, tile_opt| {
-        let i = i as u32;
-        let j = j as u32;
-        let val = match tile_opt {
-            Some(ref tile) => tile.value,
-            None           => 0,
-        };
-        let c = if val == 0 {
-            0           // or will be +Infinity
-        } else {
-            (val as f64).log2() as usize
+    let board_w = container_padding * (cols + 1) + cell_width * cols;
+    let board_h = container_padding * (rows + 1) + cell_width * rows;
+    let (x, y) = (x + (w - board_w) / 2, y + (h - board_h) / 2);
+    try!(ren.box_(x as i16, y as i16, (x+board_w) as i16, (y+board_h) as i16, CONTAINER_COLOR));
+
+    // center of a (possibly in-between) cell
+    let center = |(j, i): (f64, f64)| {
+        let step = (container_padding + cell_width) as f64;
+        (x as f64 + container_padding as f64 + cell_width as f64 / 2.0 + step * j,
+         y as f64 + container_padding as f64 + cell_width as f64 / 2.0 + step * i)
+    };
+    let lerp = |(fx, fy): (usize, usize), (tx, ty): (usize, usize), t: f64| {
+        center((fx as f64 + (tx as f64 - fx as f64) * t,
+                fy as f64 + (ty as f64 - fy as f64) * t))
+    };
+    let at = |(j, i): (usize, usize)| center((j as f64, i as f64));
+    let slide_t = (t / SLIDE_PART).min(1.0);
+    let after_t = ((t - SLIDE_PART) / (1.0 - SLIDE_PART)).max(0.0);
+    let full = cell_width as f64;
+
+    gm.grid.each_cell(|j, i, _| {
+        draw_tile(ren, font, 0, at((j, i)), full);
+    });
+    gm.grid.each_cell(|_, _, tile_opt| {
+        let tile = match tile_opt {
+            Some(tile) => tile,
+            None       => return,
         };
-        let color = CELL_COLORS.get(c).map(|&co| co).unwrap_or(SUPER_CELL_COLOR);
-        let bx = (x + container_padding * (j + 1) + cell_width * j) as i16;
-        let by = (y + container_padding * (i + 1) + cell_width * i) as i16;
-        ren.box_(bx, by, bx + cell_width as i16, by + cell_width as i16, color);
-        // ren.string(bx, by, format!("({}, {})", j, i), CHAR_COLOR); // DEBUG
-        if val != 0 {
-            let (tex, tw, th) = {
-                let wd = format!("{}", val);
-                let (w, h) = font.size(&wd[..]).ok().expect("size of str");
-                let text = font.render(&wd[..],
-                                       sdl2_ttf::RenderMode::Blended { foreground: FG_COLOR})
-                    .ok().expect("renderred surface");
-                (ren.create_texture_from_surface(&text).ok().expect("create texture"), w, h)
-            };
-
-            let ratio = if tw > cell_width {
-                cell_width as f64 / tw as f64
-            } else if th > cell_width {
-                cell_width as f64 / th as f64
-            } else { 1.0 };
-
-            let tw = (tw as f64 * ratio) as u32;
-            let th = (th as f64 * ratio) as u32;
-
-            ren.copy(&tex, None, Rect::new(bx as i32 + cell_width as i32 / 2 - tw as i32 /2,
-                                           by as i32 + cell_width as i32 / 2 - th as i32 /2,
-                                           tw, th).ok().expect("a rect"));
+        match (tile.merged_from, tile.prev_pos) {
+            (Some((a, b)), _) if t < SLIDE_PART => {
+                draw_tile(ren, font, tile.value / 2, lerp(b, tile.pos(), slide_t), full);
+                draw_tile(ren, font, tile.value / 2, lerp(a, tile.pos(), slide_t), full);
+            }
+            (Some(_), _) => {
+                let pop = 1.0 + 0.2 * (after_t * ::std::f64::consts::PI).sin();
+                draw_tile(ren, font, tile.value, at(tile.pos()), full * pop);
+            }
+            (None, Some(prev)) => {
+                draw_tile(ren, font, tile.value, lerp(prev, tile.pos(), slide_t), full);
+            }
+            (None, None) => {
+                // freshly spawned
+                draw_tile(ren, font, tile.value, at(tile.pos()), full * after_t);
+            }
         }
     });
+    Ok((x, y, board_w, board_h))
+}
+
+// An arrow along each edge of the board, pointing the way it slides, with
+// the value of the move. The best one stands out, moves that change nothing
+// are greyed out.
+fn draw_hints(ren: &mut render::Renderer, scores: &[(Direction, Option<f64>)],
+              (x,y,w,h): (u32,u32,u32,u32)) -> SdlResult<()> {
+    let best = ai::best_evaluated(scores);
+    let (cx, cy) = (x as f64 + w as f64 / 2.0, y as f64 + h as f64 / 2.0);
+    let size = 24.0;
+    for &(dir, value) in scores {
+        let (dx, dy) = dir.to_vector();
+        let (dx, dy) = (dx as f64, dy as f64);
+        let tip = (cx + dx * (w as f64 / 2.0 - 12.0), cy + dy * (h as f64 / 2.0 - 12.0));
+        let base = (tip.0 - dx * size, tip.1 - dy * size);
+        let color = match value {
+            None => HINT_ILLEGAL_COLOR,
+            Some(_) if Some(dir) == best => HINT_BEST_COLOR,
+            Some(_) => HINT_COLOR,
+        };
+        try!(ren.filled_trigon(tip.0 as i16, tip.1 as i16,
+                               (base.0 - dy * size / 2.0) as i16, (base.1 + dx * size / 2.0) as i16,
+                               (base.0 + dy * size / 2.0) as i16, (base.1 - dx * size / 2.0) as i16,
+                               color));
+        if let Some(v) = value {
+            // the gfx font is 8 pixels a character
+            let label = format!("{:.1}", v);
+            let (lx, ly) = (base.0 - dx * 16.0, base.1 - dy * 16.0);
+            let half = label.len() as f64 * 4.0;
+            try!(ren.box_((lx - half - 2.0) as i16, (ly - 6.0) as i16,
+                          (lx + half + 2.0) as i16, (ly + 6.0) as i16, HINT_LABEL_COLOR));
+            try!(ren.string((lx - half) as i16, (ly - 4.0) as i16, label.as_ref(), color));
+        }
+    }
     Ok(())
 }
 
+// Runs `job` on a thread of its own so that frames keep coming, the answer
+// comes on the returned channel.
+fn in_background<T, F>(job: F) -> Receiver<T>
+    where T: Send + 'static, F: FnOnce() -> T + Send + 'static {
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        // nobody listens anymore if the board changed meanwhile
+        let _ = tx.send(job());
+    });
+    rx
+}
+
+// Takes the answer of a background job once it is there. A job that died
+// is forgotten.
+fn poll<T>(job: &mut Option<Receiver<T>>) -> Option<T> {
+    let answer = match *job {
+        Some(ref rx) => match rx.try_recv() {
+            Ok(answer) => Some(answer),
+            Err(TryRecvError::Empty) => return None,
+            Err(TryRecvError::Disconnected) => None,
+        },
+        None => return None,
+    };
+    *job = None;
+    answer
+}
+
+// Evaluates every move in the background.
+fn request_hints(searcher: &Expectimax<WeightedHeuristic>, gm: &GameManager) -> Receiver<MoveScores> {
+    let (searcher, grid, rule) = (searcher.clone(), gm.grid.clone(), gm.spawn_rule.clone());
+    in_background(move || searcher.evaluate_moves(&grid, &rule))
+}
+
+// Plays `dir` in the background, spawns may take a search. The game goes
+// with the move, a copy without history stands in for it meanwhile.
+fn request_step(gm: &mut GameManager, dir: Direction) -> Receiver<(GameManager, Direction, MoveOutcome)> {
+    let shown = gm.fork(GameRng::from_seed(0));
+    let mut gm = mem::replace(gm, shown);
+    in_background(move || {
+        let outcome = gm.move_to(dir);
+        (gm, dir, outcome)
+    })
+}
+
+// Looks for the move autoplay makes in the background.
+fn request_move(searcher: &Expectimax<WeightedHeuristic>, gm: &GameManager) -> Receiver<Option<Direction>> {
+    let (searcher, grid, rule) = (searcher.clone(), gm.grid.clone(), gm.spawn_rule.clone());
+    in_background(move || searcher.best_move_on(&grid, &rule))
+}
+
 fn draw_title(ren: &mut render::Renderer, font: &sdl2_ttf::Font) -> SdlResult<()> {
     let (tex2, w, h) = {
         let wd = "Rust - 2048";
@@ -134,8 +299,35 @@ fn draw_popup(ren: &mut render::Renderer, font: &sdl2_ttf::Font, msg: &str) -> S
     Ok(())
 }
 
+// Spawn rules to pick from on the start screen: the one the game came
+// with, then the classic rule and the evil spawner at every difficulty.
+fn start_modes(rule: &SpawnRule) -> Vec<SpawnRule> {
+    let mut modes = vec![rule.clone()];
+    let others = iter::once(SpawnRule::default())
+        .chain(spawn::DIFFICULTIES.iter().map(|&difficulty| SpawnRule::evil(difficulty)));
+    for other in others {
+        if other != *rule {
+            modes.push(other);
+        }
+    }
+    modes
+}
+
+fn load_font() -> SdlResult<sdl2_ttf::Font> {
+    if UNDER_MACOSX {
+        sdl2_ttf::Font::from_file(&Path::new("/System/Library/Fonts/HelveticaNeueDeskInterface.ttc"), 48)
+    } else {
+        let raw_ttf_bytes: &'static [u8] = include_bytes!("./res/OpenDyslexic-Regular.ttf");
+        let raw = try!(rwops::RWops::from_bytes(raw_ttf_bytes));
+        raw.load_font(48)
+    }
+}
+
+/// `data` is where the game is saved and scores are kept, `record` where to
+/// write a replay of the game if anywhere, `animation` how long a move takes
+/// on screen, zero to disable.
 #[allow(non_shorthand_field_patterns)]
-pub fn run(game_size: usize) -> SdlResult<()> {
+pub fn run(gm: game::GameManager, data: &Path, record: Option<&Path>, animation: Duration) -> SdlResult<()> {
     let sdl_context = sdl2::init().unwrap();
     let video_subsys = sdl_context.video().unwrap();
     let _ttf_ctxt = sdl2_ttf::init();
@@ -151,60 +343,166 @@ pub fn run(game_size: usize) -> SdlResult<()> {
     let mut fpsm = sdl2_gfx::framerate::FPSManager::new();
     try!(fpsm.set_framerate(50));
 
-    let font = if UNDER_MACOSX {
-        try!(sdl2_ttf::Font::from_file(&Path::new("/System/Library/Fonts/HelveticaNeueDeskInterface.ttc"), 48))
-    } else {
-        let raw_ttf_bytes: &'static [u8] = include_bytes!("./res/OpenDyslexic-Regular.ttf");
-        let raw = try!(rwops::RWops::from_bytes(raw_ttf_bytes));
-        try!(raw.load_font(48))
-    };
+    let font = try!(load_font());
 
     // font.set_style(sdl2_ttf::STYLE_BOLD);
 
-    let mut gm = game::GameManager::new(game_size);
+    let mut runner = GameRunner::new(gm, HumanPlayer::new());
+    let mut stats = ::load_stats(data);
+    let mut recorder = Recorder::new(&runner.gm);
 
-    let mut playing = false;
+    let mut playing = runner.gm.playing;
     let mut celebrating = false;
+    let modes = start_modes(&runner.gm.spawn_rule);
+    let mut mode = 0;
 
     let mut event_pump = sdl_context.event_pump().unwrap();
 
+    let mut anim_start: Option<Instant> = None;
+    let mut autoplay = false;
+    // scores of the four moves once known, and the search still running
+    let mut hint: Option<MoveScores> = None;
+    let mut hint_rx: Option<Receiver<MoveScores>> = None;
+    let mut autoplay_rx: Option<Receiver<Option<Direction>>> = None;
+    // the move being played, the game coming back with it
+    let mut step_rx: Option<Receiver<(GameManager, Direction, MoveOutcome)>> = None;
+    // values are only good for the spawn rule they were searched under
+    let mut hint_cache = Arc::new(TranspositionTable::new(HINT_CACHE_SIZE));
+    let mut searcher = Expectimax { cache: Some(hint_cache.clone()), ..Expectimax::default() };
+
     'main : loop {
-        'event : for event in event_pump.poll_iter() {
-            fpsm.delay();
-            ren.set_draw_color(BG_COLOR);
-            ren.clear();
-            // == main drawing ==
-            draw_title(&mut ren, &font).unwrap();
-            try!(ren.string(0i16, 0i16, format!("frames: {}", fpsm.get_frame_count()).as_ref(), CHAR_COLOR));
+        fpsm.delay();
+        let t = animation_progress(anim_start, animation);
+        if t >= 1.0 {
+            anim_start = None;
+        }
+        if let Some(scores) = poll(&mut hint_rx) {
+            hint = Some(scores);
+        }
+        if let Some(Some(dir)) = poll(&mut autoplay_rx) {
+            runner.player.push(dir);
+        }
+        if let Some((gm, dir, outcome)) = poll(&mut step_rx) {
+            runner.gm = gm;
+            recorder.record(&runner.gm, dir, &outcome);
+            if outcome.moved() {
+                anim_start = Some(Instant::now());
+                hint = None;
+                hint_rx = None;
+                autoplay_rx = None;
+            }
+        }
 
-            try!(ren.string(200, 90, format!("your score: {}", gm.score).as_ref(), CHAR_COLOR));
+        ren.set_draw_color(BG_COLOR);
+        ren.clear();
+        // == main drawing ==
+        draw_title(&mut ren, &font).unwrap();
+        try!(ren.string(0i16, 0i16, format!("frames: {}", fpsm.get_frame_count()).as_ref(), CHAR_COLOR));
 
-            try!(draw_game(&mut gm, &mut ren, &font, ((SCREEN_WIDTH / 2 - 400 / 2) as u32, 100, 400, 400)));
+        try!(ren.string(200, 90, format!("your score: {}", runner.gm.score).as_ref(), CHAR_COLOR));
+        let best = cmp::max(runner.gm.score, stats.best_score(&runner.gm, &runner.gm.spawn_rule.variant()));
+        try!(ren.string(350, 90, format!("best: {}", best).as_ref(), CHAR_COLOR));
+        if autoplay {
+            try!(ren.string(500, 90, "autoplay", CHAR_COLOR));
+        } else if hint_rx.is_some() {
+            try!(ren.string(500, 90, "hint: ...", CHAR_COLOR));
+        } else if let Some(dir) = hint.as_ref().and_then(|scores| ai::best_evaluated(scores)) {
+            try!(ren.string(500, 90, format!("hint: {:?}", dir).as_ref(), CHAR_COLOR));
+        }
 
-            if celebrating || (playing && !gm.moves_available()) { // can't move
-                try!(draw_popup(&mut ren, &font, format!("Score: {}! Max Cell: {}", gm.score, "NaN").as_ref()));
-                playing = false;
-                celebrating = true;
+        let board = try!(draw_game(&runner.gm, &mut ren, &font, ((SCREEN_WIDTH / 2 - 600 / 2) as u32, 100, 600, 400), t));
+        if let Some(ref scores) = hint {
+            if anim_start.is_none() && !autoplay {
+                try!(draw_hints(&mut ren, scores, board));
+            }
+        }
 
-            } else if !playing && !celebrating {
-                draw_popup(&mut ren, &font, "Press SPACE to start!").unwrap();
+        if anim_start.is_some() {
+            // let the last move finish before any popup
+        } else if celebrating || (playing && !runner.gm.moves_available()) { // can't move
+            try!(draw_popup(&mut ren, &font, format!("Score: {}! Max Cell: {}", runner.gm.score, runner.gm.grid.max_tile()).as_ref()));
+            if playing {
+                ::record_game(&mut stats, &runner.gm, data);
+                ::save_replay(&recorder, &runner.gm, record);
             }
+            playing = false;
+            celebrating = true;
+
+        } else if playing && runner.gm.is_terminated() { // reached the target tile
+            try!(draw_popup(&mut ren, &font, "You win! Continue? (Y/N)"));
+        } else if !playing && !celebrating {
+            draw_popup(&mut ren, &font, "Press SPACE to start!").unwrap();
+        }
+        if !playing {
+            try!(ren.string((SCREEN_WIDTH / 2 - 120) as i16, (SCREEN_HEIGHT / 2 + 40) as i16,
+                            format!("mode: < {} >  (left/right)", modes[mode].variant()).as_ref(),
+                            CHAR_COLOR));
+        }
+
+        // == main drawing ends ==
+        ren.present();
 
-            // == main drawing ends ==
-            ren.present();
+        // the game is away while a move is played
+        let busy = step_rx.is_some();
+        for event in event_pump.poll_iter() {
+            let moving = playing && !runner.gm.is_terminated();
             match event {
                 Event::Quit {..} => break 'main,
-                Event::KeyDown {keycode: Some(Keycode::Left), ..} if playing => {
-                    gm.move_to(Direction::Left);
+                Event::KeyDown {keycode: Some(Keycode::Left), ..} if moving => {
+                    runner.player.push(Direction::Left);
+                }
+                Event::KeyDown {keycode: Some(Keycode::Right), ..} if moving => {
+                    runner.player.push(Direction::Right);
+                }
+                Event::KeyDown {keycode: Some(Keycode::Up), ..} if moving => {
+                    runner.player.push(Direction::Up);
                 }
-                Event::KeyDown {keycode: Some(Keycode::Right), ..} if playing => {
-                    gm.move_to(Direction::Right);
+                Event::KeyDown {keycode: Some(Keycode::Down), ..} if moving => {
+                    runner.player.push(Direction::Down);
                 }
-                Event::KeyDown {keycode: Some(Keycode::Up), ..} if playing => {
-                    gm.move_to(Direction::Up);
+                Event::KeyDown {keycode: Some(Keycode::Left), ..} if !playing => {
+                    mode = (mode + modes.len() - 1) % modes.len();
                 }
-                Event::KeyDown {keycode: Some(Keycode::Down), ..} if playing => {
-                    gm.move_to(Direction::Down);
+                Event::KeyDown {keycode: Some(Keycode::Right), ..} if !playing => {
+                    mode = (mode + 1) % modes.len();
+                }
+                Event::KeyDown {keycode: Some(Keycode::U), ..} if playing && !busy => {
+                    runner.player.clear();
+                    anim_start = None;
+                    hint = None;
+                    hint_rx = None;
+                    autoplay_rx = None;
+                    runner.gm.undo();
+                }
+                Event::KeyDown {keycode: Some(Keycode::R), ..} if playing && !busy => {
+                    runner.player.clear();
+                    anim_start = None;
+                    hint = None;
+                    hint_rx = None;
+                    autoplay_rx = None;
+                    runner.gm.redo();
+                }
+                Event::KeyDown {keycode: Some(Keycode::H), ..} if moving => {
+                    if hint.is_some() || hint_rx.is_some() {
+                        hint = None;
+                        hint_rx = None;
+                    } else {
+                        hint_rx = Some(request_hints(&searcher, &runner.gm));
+                    }
+                }
+                Event::KeyDown {keycode: Some(Keycode::A), ..} => {
+                    autoplay = !autoplay;
+                    autoplay_rx = None;
+                    runner.player.clear();
+                }
+                Event::KeyDown {keycode: Some(Keycode::Y), ..} if playing && !busy && runner.gm.won => {
+                    runner.gm.keep_playing();
+                }
+                Event::KeyDown {keycode: Some(Keycode::N), ..} if playing && !busy && runner.gm.is_terminated() => {
+                    ::record_game(&mut stats, &runner.gm, data);
+                    ::save_replay(&recorder, &runner.gm, record);
+                    playing = false;
+                    celebrating = true;
                 }
                 Event::KeyDown {keycode: key, ..} => {
                     if key == Some(Keycode::Escape) {
@@ -213,18 +511,186 @@ pub fn run(game_size: usize) -> SdlResult<()> {
                         if !playing {
                             playing = true;
                             celebrating = false;
-                            gm.setup();
+                            runner.player.clear();
+                            if runner.gm.spawn_rule != modes[mode] {
+                                // a fresh table, searches still running may yet fill the old one
+                                hint_cache = Arc::new(TranspositionTable::new(HINT_CACHE_SIZE));
+                                searcher.cache = Some(hint_cache.clone());
+                            }
+                            runner.gm.spawn_rule = modes[mode].clone();
+                            runner.gm.setup();
+                            hint = None;
+                            hint_rx = None;
+                            autoplay_rx = None;
+                            recorder = Recorder::new(&runner.gm);
+                            anim_start = Some(Instant::now());
                         }
                     }
 
                 }
                 Event::MouseButtonDown {x: x, y: y, ..} => {
-                    println!("mouse btn down at ({},{})", x, y);
+                    debug!("mouse btn down at ({},{})", x, y);
                 }
 
                 _ => {}
             }
         }
+
+        if anim_start.is_none() && step_rx.is_none() {
+            if autoplay && autoplay_rx.is_none() && !runner.player.has_pending() &&
+                playing && !runner.gm.is_terminated() {
+                autoplay_rx = Some(request_move(&searcher, &runner.gm));
+            }
+            // moves typed while an animation runs are played once it is over
+            if !playing || runner.gm.is_terminated() {
+                runner.player.clear();
+            } else if let Some(dir) = runner.next_move() {
+                step_rx = Some(request_step(&mut runner.gm, dir));
+            }
+        }
+    }
+    if let Some(rx) = step_rx {
+        if let Ok((gm, dir, outcome)) = rx.recv() {
+            runner.gm = gm;
+            recorder.record(&runner.gm, dir, &outcome);
+        }
+    }
+    ::autosave(&runner.gm, playing, data);
+    if playing {
+        ::save_replay(&recorder, &runner.gm, record);
+    }
+    info!("hint cache: {:?}", hint_cache.stats());
+    Ok(())
+}
+
+fn digit(key: Keycode) -> Option<char> {
+    match key {
+        Keycode::Num0 | Keycode::Kp0 => Some('0'),
+        Keycode::Num1 | Keycode::Kp1 => Some('1'),
+        Keycode::Num2 | Keycode::Kp2 => Some('2'),
+        Keycode::Num3 | Keycode::Kp3 => Some('3'),
+        Keycode::Num4 | Keycode::Kp4 => Some('4'),
+        Keycode::Num5 | Keycode::Kp5 => Some('5'),
+        Keycode::Num6 | Keycode::Kp6 => Some('6'),
+        Keycode::Num7 | Keycode::Kp7 => Some('7'),
+        Keycode::Num8 | Keycode::Kp8 => Some('8'),
+        Keycode::Num9 | Keycode::Kp9 => Some('9'),
+        _ => None,
+    }
+}
+
+/// Plays back a verified replay, paused on its first position. Left / Right
+/// step, Space plays or pauses, +/- change the speed, Home / End go to
+/// either end, a move number then Enter jumps to it.
+pub fn run_replay(playback: Playback, animation: Duration) -> SdlResult<()> {
+    let sdl_context = sdl2::init().unwrap();
+    let video_subsys = sdl_context.video().unwrap();
+    let _ttf_ctxt = sdl2_ttf::init();
+
+    let win = video_subsys.window("Rust - 2048 replay", SCREEN_WIDTH as u32, SCREEN_HEIGHT as u32)
+        .position_centered()
+        .opengl()
+        .build()
+        .unwrap();
+
+    let mut ren = win.renderer().accelerated().build().unwrap();
+
+    let mut fpsm = sdl2_gfx::framerate::FPSManager::new();
+    try!(fpsm.set_framerate(50));
+    let font = try!(load_font());
+
+    let mut playback = playback;
+    let mut event_pump = sdl_context.event_pump().unwrap();
+    let mut paused = true;
+    let mut interval_ms = 500;
+    let mut last_step = Instant::now();
+    let mut goto = String::new();
+    let mut anim_start: Option<Instant> = None;
+
+    'main : loop {
+        fpsm.delay();
+        // never animate longer than the delay between two moves
+        let t = animation_progress(anim_start, cmp::min(animation, Duration::from_millis(interval_ms)));
+        if t >= 1.0 {
+            anim_start = None;
+        }
+
+        ren.set_draw_color(BG_COLOR);
+        ren.clear();
+        draw_title(&mut ren, &font).unwrap();
+        try!(ren.string(200, 90, format!("move {}/{}", playback.position(), playback.len()).as_ref(), CHAR_COLOR));
+        try!(ren.string(350, 90, format!("score: {}", playback.game().score).as_ref(), CHAR_COLOR));
+        let state = if !goto.is_empty() {
+            format!("go to: {}", goto)
+        } else if paused {
+            "paused".to_string()
+        } else {
+            format!("every {} ms", interval_ms)
+        };
+        try!(ren.string(500, 90, state.as_ref(), CHAR_COLOR));
+        try!(draw_game(playback.game(), &mut ren, &font, ((SCREEN_WIDTH / 2 - 600 / 2) as u32, 100, 600, 400), t));
+        ren.present();
+
+        for event in event_pump.poll_iter() {
+            match event {
+                Event::Quit {..} | Event::KeyDown {keycode: Some(Keycode::Escape), ..} => break 'main,
+                Event::KeyDown {keycode: Some(Keycode::Right), ..} => {
+                    if playback.step_forward().is_some() {
+                        anim_start = Some(Instant::now());
+                    }
+                }
+                Event::KeyDown {keycode: Some(Keycode::Left), ..} => {
+                    playback.step_back();
+                    anim_start = None;
+                }
+                Event::KeyDown {keycode: Some(Keycode::Space), ..} => {
+                    paused = !paused;
+                    last_step = Instant::now();
+                }
+                Event::KeyDown {keycode: Some(Keycode::Equals), ..} |
+                Event::KeyDown {keycode: Some(Keycode::KpPlus), ..} => {
+                    interval_ms = cmp::max(interval_ms / 2, MIN_INTERVAL_MS);
+                }
+                Event::KeyDown {keycode: Some(Keycode::Minus), ..} |
+                Event::KeyDown {keycode: Some(Keycode::KpMinus), ..} => {
+                    interval_ms = cmp::min(interval_ms * 2, MAX_INTERVAL_MS);
+                }
+                Event::KeyDown {keycode: Some(Keycode::Home), ..} => {
+                    playback.seek(0);
+                    anim_start = None;
+                }
+                Event::KeyDown {keycode: Some(Keycode::End), ..} => {
+                    let end = playback.len();
+                    playback.seek(end);
+                    anim_start = None;
+                }
+                Event::KeyDown {keycode: Some(Keycode::Backspace), ..} => {
+                    goto.pop();
+                }
+                Event::KeyDown {keycode: Some(Keycode::Return), ..} |
+                Event::KeyDown {keycode: Some(Keycode::KpEnter), ..} => {
+                    if let Ok(n) = goto.parse() {
+                        playback.seek(n);
+                        anim_start = None;
+                    }
+                    goto.clear();
+                }
+                Event::KeyDown {keycode: Some(key), ..} => {
+                    if let Some(c) = digit(key) {
+                        goto.push(c);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        if !paused && anim_start.is_none() && last_step.elapsed() >= Duration::from_millis(interval_ms) {
+            match playback.step_forward() {
+                Some(_) => anim_start = Some(Instant::now()),
+                None => paused = true,
+            }
+            last_step = Instant::now();
+        }
     }
     Ok(())
 }
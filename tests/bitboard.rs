@@ -0,0 +1,86 @@
+#[macro_use]
+extern crate quickcheck;
+extern crate game2048;
+
+mod common;
+
+use game2048::{BitBoard, Direction};
+use common::{direction, grid, played};
+
+fn board(rows: &[&[isize]]) -> BitBoard {
+    BitBoard::from_grid(&grid(rows)).unwrap()
+}
+
+#[test]
+fn rows_slide_and_merge_once() {
+    let start = board(&[&[2, 2, 4, 8],
+                        &[2, 2, 2, 2],
+                        &[0, 2, 0, 2],
+                        &[2, 4, 8, 16]]);
+    assert_eq!(start.slide(Direction::Left), Some((board(&[&[4, 4, 8, 0],
+                                                           &[4, 4, 0, 0],
+                                                           &[4, 0, 0, 0],
+                                                           &[2, 4, 8, 16]]), 16)));
+    assert_eq!(start.slide(Direction::Right), Some((board(&[&[0, 4, 4, 8],
+                                                            &[0, 0, 4, 4],
+                                                            &[0, 0, 0, 4],
+                                                            &[2, 4, 8, 16]]), 16)));
+    assert_eq!(start.slide(Direction::Up), Some((board(&[&[4, 4, 4, 8],
+                                                         &[2, 2, 2, 4],
+                                                         &[0, 4, 8, 16],
+                                                         &[0, 0, 0, 0]]), 12)));
+}
+
+#[test]
+fn stuck_board() {
+    let stuck = board(&[&[2, 4, 2, 4],
+                        &[4, 2, 4, 2],
+                        &[2, 4, 2, 4],
+                        &[4, 2, 4, 2]]);
+    assert!(Direction::all_directions().into_iter().all(|dir| stuck.slide(dir).is_none()));
+    assert!(!stuck.moves_available());
+    assert_eq!(stuck.count_empty(), 0);
+}
+
+#[test]
+fn packing() {
+    let rows: &[&[isize]] = &[&[2, 0, 0, 1 << 15],
+                              &[0, 4, 0, 0],
+                              &[0, 0, 0, 0],
+                              &[8, 0, 0, 2048]];
+    let packed = board(rows);
+    assert_eq!(packed.to_grid(), grid(rows));
+    assert_eq!((packed.get((3, 0)), packed.get((0, 3)), packed.max_exponent()), (15, 3, 15));
+    assert_eq!(BitBoard::from_grid(&grid(&[&[1 << 16, 0, 0, 0], &[0; 4], &[0; 4], &[0; 4]])), None);
+    assert_eq!(BitBoard::from_grid(&grid(&[&[2, 0, 0], &[0; 3], &[0; 3]])), None);
+}
+
+quickcheck! {
+    // Plays a seeded game and checks every move against the bitboard.
+    fn slide_matches_grid(seed: u64, moves: Vec<u8>) -> bool {
+        let mut gm = played(4, seed, &[]);
+        for &m in moves.iter() {
+            let dir = direction(m);
+            let before = BitBoard::from_grid(&gm.grid).unwrap();
+            // the same tiles, if not their last move
+            if before.to_grid().to_string() != gm.grid.to_string()
+                || BitBoard::from_grid(&before.to_grid()) != Some(before) {
+                return false;
+            }
+            let outcome = gm.move_to(dir);
+            let mut after = gm.grid.clone();
+            for &tile in outcome.spawned.iter() {
+                after.remove_tile(tile);
+            }
+            let expected = if outcome.moved() {
+                Some((BitBoard::from_grid(&after).unwrap(), outcome.score_delta as u32))
+            } else {
+                None
+            };
+            if before.slide(dir) != expected {
+                return false;
+            }
+        }
+        true
+    }
+}
@@ -0,0 +1,78 @@
+//! Boards and games the integration tests start from.
+
+// every test crate uses its own share of these
+#![allow(dead_code)]
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+
+use game2048::{Direction, GameManager, Grid, Tile};
+
+/// A file in the temp directory, removed once the test is done with it.
+pub struct Scratch {
+    path: PathBuf
+}
+
+/// A scratch file of this test run, `name` telling its tests apart.
+pub fn scratch(name: &str) -> Scratch {
+    Scratch { path: env::temp_dir().join(format!("game2048-{}-{}", process::id(), name)) }
+}
+
+impl AsRef<Path> for Scratch {
+    fn as_ref(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for Scratch {
+    fn drop(&mut self) {
+        // the test may have never written it
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+/// The direction a random byte stands for.
+pub fn direction(n: u8) -> Direction {
+    Direction::all_directions()[n as usize % 4]
+}
+
+/// A `size` x `size` game from `seed`, after playing `moves`.
+pub fn played(size: usize, seed: u64, moves: &[u8]) -> GameManager {
+    let mut gm = GameManager::with_seed(size, seed);
+    gm.setup();
+    for &m in moves {
+        gm.move_to(direction(m));
+    }
+    gm
+}
+
+/// A board from its rows of values, 0 for an empty cell.
+pub fn grid(rows: &[&[isize]]) -> Grid {
+    let mut grid = Grid::with_dimensions(rows[0].len(), rows.len());
+    for (y, row) in rows.iter().enumerate() {
+        for (x, &value) in row.iter().enumerate() {
+            if value != 0 {
+                grid.insert_tile(Tile::new((x, y), value));
+            }
+        }
+    }
+    grid
+}
+
+/// A few tiles that no move merges.
+pub fn start() -> Grid {
+    grid(&[&[2, 4, 0, 0],
+           &[0, 0, 0, 0],
+           &[0, 0, 0, 8],
+           &[0, 0, 0, 0]])
+}
+
+/// A board every move merges something on.
+pub fn merging() -> Grid {
+    grid(&[&[2, 2, 0, 4],
+           &[0, 0, 0, 4],
+           &[2, 0, 0, 0],
+           &[0, 8, 8, 0]])
+}
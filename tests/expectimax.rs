@@ -0,0 +1,77 @@
+extern crate game2048;
+
+mod common;
+
+use game2048::{Direction, SpawnRule};
+use game2048::ai::{self, Expectimax};
+
+use common::grid;
+
+fn value(scores: &[(Direction, Option<f64>)], dir: Direction) -> Option<f64> {
+    scores.iter().find(|&&(d, _)| d == dir).and_then(|&(_, v)| v)
+}
+
+#[test]
+fn moves_changing_nothing_have_no_value() {
+    let searcher = Expectimax { depth: 1, ..Expectimax::default() };
+    let scores = searcher.evaluate_moves(&grid(&[&[2, 4, 0, 0],
+                                                  &[4, 2, 0, 0],
+                                                  &[0, 0, 0, 0],
+                                                  &[0, 0, 0, 0]]), &SpawnRule::default());
+    assert_eq!(scores.iter().map(|&(dir, _)| dir).collect::<Vec<_>>(), Direction::all_directions());
+    assert_eq!(value(&scores, Direction::Up), None);
+    assert_eq!(value(&scores, Direction::Left), None);
+    assert!(value(&scores, Direction::Right).is_some());
+    assert!(value(&scores, Direction::Down).is_some());
+
+    let stuck = grid(&[&[2, 4, 2, 4],
+                       &[4, 2, 4, 2],
+                       &[2, 4, 2, 4],
+                       &[4, 2, 4, 2]]);
+    assert!(searcher.evaluate_moves(&stuck, &SpawnRule::default()).iter().all(|&(_, v)| v.is_none()));
+    assert_eq!(searcher.best_move_on(&stuck, &SpawnRule::default()), None);
+}
+
+#[test]
+fn merging_into_a_corner_is_worth_most() {
+    let searcher = Expectimax { depth: 1, ..Expectimax::default() };
+    let scores = searcher.evaluate_moves(&grid(&[&[1024, 1024, 0, 0],
+                                                  &[0,    0,    0, 0],
+                                                  &[0,    0,    0, 0],
+                                                  &[0,    0,    0, 0]]), &SpawnRule::default());
+    let (left, right, down) = (value(&scores, Direction::Left).unwrap(),
+                               value(&scores, Direction::Right).unwrap(),
+                               value(&scores, Direction::Down).unwrap());
+    assert_eq!(value(&scores, Direction::Up), None);
+    assert!(left > down && right > down, "{:?}", scores);
+    assert!(ai::best_evaluated(&scores) == Some(Direction::Left) ||
+            ai::best_evaluated(&scores) == Some(Direction::Right));
+}
+
+#[test]
+fn spawns_weigh_as_the_rule_says() {
+    let searcher = Expectimax { depth: 1, ..Expectimax::default() };
+    let board = grid(&[&[2, 4, 8, 16],
+                       &[4, 8, 16, 32],
+                       &[8, 16, 32, 64],
+                       &[0, 0, 0, 2]]);
+    let left = |rule: &SpawnRule| value(&searcher.evaluate_moves(&board, rule), Direction::Left).unwrap();
+    let classic = left(&SpawnRule::default());
+    let twos = left(&SpawnRule { values: vec![(2, 1)], ..SpawnRule::default() });
+    let fours = left(&SpawnRule { values: vec![(4, 1)], ..SpawnRule::default() });
+    assert!(classic != twos && classic != fours && twos != fours);
+    // the classic weights make it the 90/10 mix of the two
+    assert!((classic - (0.9 * twos + 0.1 * fours)).abs() < 1e-9);
+}
+
+#[test]
+fn highlighted_hint() {
+    use game2048::Direction::*;
+    assert_eq!(ai::best_evaluated(&[(Up, Some(1.0)), (Right, Some(3.0)), (Down, None), (Left, Some(2.0))]),
+               Some(Right));
+    // ties go to the first
+    assert_eq!(ai::best_evaluated(&[(Up, None), (Right, Some(-1.0)), (Down, Some(-1.0)), (Left, None)]),
+               Some(Right));
+    assert_eq!(ai::best_evaluated(&[(Up, None), (Right, None), (Down, None), (Left, None)]), None);
+    assert_eq!(ai::best_evaluated(&[]), None);
+}
@@ -0,0 +1,113 @@
+extern crate game2048;
+
+mod common;
+
+use game2048::{Direction, GameManager, TileMerge, TileMove};
+
+use common::{direction, grid, played};
+
+#[test]
+fn outcome_of_a_move() {
+    let mut gm = GameManager::with_seed(4, 6);
+    gm.grid = grid(&[&[2, 2, 4, 0],
+                     &[0, 0, 0, 8],
+                     &[4, 0, 4, 0],
+                     &[0, 0, 0, 0]]);
+    let outcome = gm.move_to(Direction::Left);
+    assert_eq!(outcome.moves, vec![TileMove { from: (2, 0), to: (1, 0) },
+                                   TileMove { from: (3, 1), to: (0, 1) }]);
+    // the moving tile first, then the one it merged into
+    assert_eq!(outcome.merges, vec![TileMerge { sources: ((1, 0), (0, 0)), result: (0, 0), value: 4 },
+                                    TileMerge { sources: ((2, 2), (0, 2)), result: (0, 2), value: 8 }]);
+    assert_eq!(outcome.score_delta, 12);
+    assert_eq!(outcome.spawned.len(), 1);
+
+    let spawned = outcome.spawned[0];
+    gm.grid.remove_tile(spawned);
+    assert_eq!(gm.grid.zobrist(), grid(&[&[4, 4, 0, 0],
+                                         &[8, 0, 0, 0],
+                                         &[8, 0, 0, 0],
+                                         &[0, 0, 0, 0]]).zobrist());
+}
+
+#[test]
+fn merging_past_the_target_wins() {
+    let mut gm = GameManager::with_seed(4, 2);
+    gm.target_tile = 32;
+    gm.grid = grid(&[&[64, 64, 0, 0],
+                     &[0,  0,  0, 0],
+                     &[0,  0,  0, 0],
+                     &[0,  0,  0, 0]]);
+    assert!(!gm.won);
+    let outcome = gm.move_to(Direction::Left);
+    assert!(outcome.won && gm.won);
+    assert!(gm.is_terminated());
+}
+
+#[test]
+fn merging_below_the_target_does_not_win() {
+    let mut gm = GameManager::with_seed(4, 2);
+    gm.target_tile = 256;
+    gm.grid = grid(&[&[64, 64, 0, 0],
+                     &[0,  0,  0, 0],
+                     &[0,  0,  0, 0],
+                     &[0,  0,  0, 0]]);
+    assert!(!gm.move_to(Direction::Left).won);
+    assert!(!gm.won);
+}
+
+#[test]
+fn grid_display() {
+    let board = grid(&[&[2, 0],
+                       &[0, 2048]]);
+    assert_eq!(board.to_string(), "[   2][    ]\n[    ][2048]\n");
+}
+
+#[test]
+fn seeds_replay_games() {
+    let moves = [0, 1, 2, 3, 1, 1, 2, 0, 3, 2];
+    let (a, b) = (played(4, 42, &moves), played(4, 42, &moves));
+    assert_eq!(a.grid, b.grid);
+    assert_eq!((a.score, a.moves, a.seed()), (b.score, b.moves, b.seed()));
+
+    let other = played(4, 43, &moves);
+    assert!(other.grid != a.grid);
+}
+
+#[test]
+fn undo_takes_back_the_spawn() {
+    let mut gm = played(4, 8, &[0, 1]);
+    let (grid, score) = (gm.grid.clone(), gm.score);
+    let outcome = gm.move_to(Direction::Down);
+    assert!(outcome.moved() && !outcome.spawned.is_empty());
+    let after = gm.grid.clone();
+
+    assert!(gm.undo());
+    assert_eq!(gm.grid, grid);
+    assert_eq!(gm.score, score);
+
+    assert!(gm.redo());
+    assert_eq!(gm.grid, after);
+    assert!(gm.undo());
+
+    // the rng is back too, the same move spawns the same tile
+    assert_eq!(gm.move_to(Direction::Down).spawned, outcome.spawned);
+    assert_eq!(gm.grid, after);
+    assert_eq!(gm.redo_available(), 0);
+}
+
+#[test]
+fn no_history_plays_the_same() {
+    let moves = [0, 1, 2, 3, 1, 1, 2, 0];
+    let kept = played(4, 13, &moves);
+    let mut gm = GameManager::with_seed(4, 13);
+    gm.history_depth = 0;
+    gm.setup();
+    for &m in moves.iter() {
+        gm.move_to(direction(m));
+    }
+    assert_eq!(gm.grid, kept.grid);
+    assert_eq!(gm.score, kept.score);
+    assert_eq!(gm.undo_available(), 0);
+    assert!(!gm.undo());
+}
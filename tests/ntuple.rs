@@ -0,0 +1,59 @@
+extern crate game2048;
+
+mod common;
+
+use game2048::{GameManager, Grid, NTupleNetwork};
+use game2048::ntuple;
+
+use common::scratch;
+
+fn network() -> NTupleNetwork {
+    NTupleNetwork::new(4, 4, ntuple::parse_tuples("small", 4, 4).unwrap(), 0.0).unwrap()
+}
+
+fn trained(games: u64) -> NTupleNetwork {
+    let mut network = network();
+    for seed in 0..games {
+        let mut gm = GameManager::with_seed(4, seed);
+        gm.setup();
+        network.train_game(&mut gm, 0.1);
+    }
+    network
+}
+
+// Boards met in a game `network` plays.
+fn boards(network: &NTupleNetwork, seed: u64) -> Vec<Grid> {
+    let mut gm = GameManager::with_seed(4, seed);
+    gm.setup();
+    gm.keep_playing();
+    let mut boards = vec![gm.grid.clone()];
+    while let Some(dir) = network.best_move(&gm.grid) {
+        gm.move_to(dir);
+        boards.push(gm.grid.clone());
+    }
+    boards
+}
+
+#[test]
+fn training_changes_play() {
+    let (fresh, trained) = (network(), trained(20));
+    assert_eq!(trained.games(), 20);
+    let boards = boards(&trained, 1000);
+    assert!(boards.iter().all(|grid| fresh.value(grid) == 0.0));
+    assert!(boards.iter().any(|grid| trained.value(grid) != 0.0));
+    assert!(boards.iter().any(|grid| fresh.best_move(grid) != trained.best_move(grid)));
+}
+
+#[test]
+fn weights_round_trip() {
+    let network = trained(5);
+    let path = scratch("ntuple.bin");
+    network.save(&path).unwrap();
+    let loaded = NTupleNetwork::load(&path).unwrap();
+    assert_eq!((loaded.width(), loaded.height(), loaded.games()), (4, 4, 5));
+    assert_eq!(loaded.tuples(), network.tuples());
+    for grid in boards(&network, 1000) {
+        assert_eq!(loaded.value(&grid), network.value(&grid));
+        assert_eq!(loaded.best_move(&grid), network.best_move(&grid));
+    }
+}
@@ -0,0 +1,69 @@
+extern crate game2048;
+
+mod common;
+
+use game2048::{Direction, GameManager, GameRunner};
+use game2048::player::{ScriptedPlayer, GreedyPlayer, CornerPlayer};
+
+use common::grid;
+
+// A fresh game on a hand-built board, spawns drawn from `seed`.
+fn game(seed: u64, rows: &[&[isize]]) -> GameManager {
+    let mut gm = GameManager::with_seed(4, seed);
+    gm.grid = grid(rows);
+    gm
+}
+
+fn left_column() -> GameManager {
+    game(3, &[&[2, 0, 0, 0],
+              &[4, 0, 0, 0],
+              &[0, 0, 0, 0],
+              &[0, 0, 0, 0]])
+}
+
+#[test]
+fn script_stops_at_illegal_move() {
+    let gm = left_column();
+    let mut runner = GameRunner::new(gm.clone(), ScriptedPlayer::new(vec![Direction::Left, Direction::Right]));
+    runner.run();
+    // the failed move leaves the tiles where they were
+    assert_eq!(runner.gm.grid.zobrist(), gm.grid.zobrist());
+    assert_eq!((runner.gm.moves, runner.gm.score), (0, 0));
+}
+
+#[test]
+fn script_plays_like_hand() {
+    let script = vec![Direction::Right, Direction::Down, Direction::Left, Direction::Up];
+    let mut gm = left_column();
+    let mut runner = GameRunner::new(gm.clone(), ScriptedPlayer::new(script.clone()));
+    for &dir in script.iter() {
+        let (played, outcome) = runner.step().unwrap();
+        assert_eq!(played, dir);
+        assert_eq!(outcome.moved(), gm.move_to(dir).moved());
+        assert_eq!(runner.gm.grid, gm.grid);
+        assert_eq!(runner.gm.score, gm.score);
+    }
+    assert!(runner.step().is_none());
+}
+
+#[test]
+fn next_move_does_not_play() {
+    let gm = left_column();
+    let mut runner = GameRunner::new(gm.clone(), CornerPlayer);
+    // down before left, the tiles being in the left column already
+    assert_eq!(runner.next_move(), Some(Direction::Down));
+    assert_eq!(runner.gm.grid, gm.grid);
+    assert_eq!(runner.gm.moves, 0);
+}
+
+#[test]
+fn greedy_takes_the_merge() {
+    let gm = game(5, &[&[0, 0, 0, 0],
+                       &[0, 0, 0, 0],
+                       &[2, 0, 0, 0],
+                       &[2, 4, 0, 0]]);
+    let mut runner = GameRunner::new(gm, GreedyPlayer);
+    let (dir, outcome) = runner.step().unwrap();
+    assert!(dir == Direction::Up || dir == Direction::Down);
+    assert_eq!(outcome.score_delta, 4);
+}
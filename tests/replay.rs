@@ -0,0 +1,92 @@
+extern crate game2048;
+#[macro_use]
+extern crate serde_json;
+
+mod common;
+
+use std::fs::File;
+
+use serde_json::Value;
+
+use game2048::{Playback, Recorder, Replay, ReplayError, SaveError};
+
+use common::{direction, played, scratch};
+
+// A short recorded game, as JSON to spoil.
+fn recorded(name: &str) -> Value {
+    let mut gm = played(4, 5, &[]);
+    let mut recorder = Recorder::new(&gm);
+    for &m in [0, 1, 2].iter() {
+        let outcome = gm.move_to(direction(m));
+        recorder.record(&gm, direction(m), &outcome);
+    }
+    let path = scratch(&format!("{}.json", name));
+    recorder.replay(&gm).save(&path).unwrap();
+    serde_json::from_reader(File::open(&path).unwrap()).unwrap()
+}
+
+fn load(name: &str, replay: &Value) -> Result<Replay, ReplayError> {
+    let path = scratch(&format!("{}.json", name));
+    serde_json::to_writer(File::create(&path).unwrap(), replay).unwrap();
+    Replay::load(&path)
+}
+
+#[test]
+fn playback_ends_where_the_game_did() {
+    let mut gm = played(4, 21, &[0, 3]);
+    let mut recorder = Recorder::new(&gm);
+    for (i, &m) in [1, 2, 2, 3, 0, 1, 2, 3, 3, 0, 2, 1].iter().enumerate() {
+        let dir = direction(m);
+        let outcome = gm.move_to(dir);
+        recorder.record(&gm, dir, &outcome);
+        // some moves are taken back and replaced
+        if i % 5 == 4 {
+            gm.undo();
+        }
+    }
+
+    let path = scratch("playback.json");
+    recorder.replay(&gm).save(&path).unwrap();
+    let mut playback = Playback::new(Replay::load(&path).unwrap()).unwrap();
+    assert_eq!(playback.len(), gm.moves - 2);
+
+    playback.seek(playback.len());
+    assert_eq!(playback.game().grid, gm.grid);
+    assert_eq!((playback.game().score, playback.game().moves), (gm.score, gm.moves - 2));
+
+    playback.seek(0);
+    assert_eq!(playback.game().grid.zobrist(), played(4, 21, &[0, 3]).grid.zobrist());
+}
+
+#[test]
+fn start_position_is_checked() {
+    assert!(load("sound", &recorded("sound")).is_ok());
+
+    let mut replay = recorded("board");
+    replay["width"] = json!(100000000);
+    match load("board", &replay) {
+        Err(ReplayError::Game(SaveError::BoardSize(100000000, 4))) => (),
+        other => panic!("{:?}", other),
+    }
+
+    let mut replay = recorded("target");
+    replay["target_tile"] = json!(3);
+    match load("target", &replay) {
+        Err(ReplayError::Game(SaveError::TargetTile(3))) => (),
+        other => panic!("{:?}", other),
+    }
+
+    let mut replay = recorded("rule");
+    replay["spawn_rule"] = json!({"values": []});
+    match load("rule", &replay) {
+        Err(ReplayError::Game(SaveError::SpawnRule(_))) => (),
+        other => panic!("{:?}", other),
+    }
+
+    let mut replay = recorded("rng");
+    replay["rng"]["state"] = json!(0);
+    match load("rng", &replay) {
+        Err(ReplayError::Game(SaveError::Rng)) => (),
+        other => panic!("{:?}", other),
+    }
+}
@@ -0,0 +1,33 @@
+extern crate game2048;
+
+use game2048::{Direction, GameManager, GameView};
+use game2048::rollout::{Budget, RolloutPlayer};
+
+fn game() -> GameManager {
+    let mut gm = GameManager::with_seed(4, 3);
+    gm.setup();
+    for &dir in [Direction::Left, Direction::Up, Direction::Left].iter() {
+        gm.move_to(dir);
+    }
+    gm
+}
+
+fn evaluate(threads: usize, rollouts: usize, seed: u64) -> Vec<(Direction, f64)> {
+    let mut player = RolloutPlayer::new(seed);
+    player.budget = Budget::Rollouts(rollouts);
+    player.threads = threads;
+    player.evaluate(&GameView::new(&game()))
+}
+
+#[test]
+fn threads_value_every_legal_move() {
+    let gm = game();
+    let legal = GameView::new(&gm).legal_moves();
+    for &threads in [2, 3, 8].iter() {
+        let scores = evaluate(threads, 5, 42);
+        assert_eq!(scores.iter().map(|&(dir, _)| dir).collect::<Vec<_>>(), legal);
+        assert!(scores.iter().all(|&(_, score)| score > gm.score as f64), "{:?}", scores);
+        // same seed, same split of the playouts: same values
+        assert_eq!(scores, evaluate(threads, 5, 42));
+    }
+}
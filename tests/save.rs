@@ -0,0 +1,195 @@
+extern crate game2048;
+#[macro_use]
+extern crate serde_json;
+
+mod common;
+
+use std::fs::File;
+use std::io::Write;
+
+use serde_json::Value;
+
+use game2048::{Direction, GameManager, SaveError};
+
+use common::scratch;
+
+fn game() -> GameManager {
+    let mut gm = GameManager::with_seed(4, 7);
+    gm.setup();
+    gm.move_to(Direction::Left);
+    gm.move_to(Direction::Up);
+    gm
+}
+
+// The save file of `game()`, as JSON to spoil.
+fn saved(name: &str) -> Value {
+    let path = scratch(&format!("{}.json", name));
+    game().save(&path).unwrap();
+    serde_json::from_reader(File::open(&path).unwrap()).unwrap()
+}
+
+fn load(name: &str, save: &Value) -> Result<GameManager, SaveError> {
+    let path = scratch(&format!("{}.json", name));
+    serde_json::to_writer(File::create(&path).unwrap(), save).unwrap();
+    GameManager::load(&path)
+}
+
+// Some tile of the saved grid.
+fn some_tile(save: &mut Value) -> &mut Value {
+    save["game"]["grid"]["cells"].as_array_mut().unwrap().iter_mut()
+        .flat_map(|col| col.as_array_mut().unwrap().iter_mut())
+        .find(|cell| !cell.is_null())
+        .unwrap()
+}
+
+#[test]
+fn round_trip() {
+    let gm = load("round-trip", &saved("round-trip")).unwrap();
+    let expected = game();
+    assert_eq!(gm.grid, expected.grid);
+    assert_eq!((gm.score, gm.moves, gm.seed()), (expected.score, expected.moves, expected.seed()));
+}
+
+#[test]
+fn missing_file() {
+    match GameManager::load(scratch("missing.json")) {
+        Err(SaveError::Io(_)) => (),
+        other => panic!("{:?}", other),
+    }
+}
+
+#[test]
+fn malformed_file() {
+    let path = scratch("malformed.json");
+    File::create(&path).unwrap().write_all(b"{\"version\": 2, \"game\": [").unwrap();
+    match GameManager::load(&path) {
+        Err(SaveError::Format(_)) => (),
+        other => panic!("{:?}", other),
+    }
+}
+
+#[test]
+fn unknown_version() {
+    let mut save = saved("version");
+    save["version"] = json!(99);
+    match load("version", &save) {
+        Err(SaveError::Version(99)) => (),
+        other => panic!("{:?}", other),
+    }
+}
+
+#[test]
+fn board_out_of_range() {
+    for &(width, height) in [(0, 0), (1, 4), (4, 9)].iter() {
+        let mut save = saved("board");
+        save["game"]["width"] = json!(width);
+        save["game"]["height"] = json!(height);
+        match load("board", &save) {
+            Err(SaveError::BoardSize(w, h)) => assert_eq!((w as u64, h as u64), (width, height)),
+            other => panic!("{}x{}: {:?}", width, height, other),
+        }
+    }
+}
+
+#[test]
+fn grid_of_another_size() {
+    let mut save = saved("grid");
+    save["game"]["width"] = json!(5);
+    match load("grid", &save) {
+        Err(SaveError::GridSize(5, 4)) => (),
+        other => panic!("{:?}", other),
+    }
+}
+
+#[test]
+fn tile_value() {
+    let mut save = saved("value");
+    some_tile(&mut save)["value"] = json!(3);
+    match load("value", &save) {
+        Err(SaveError::TileValue(_, 3)) => (),
+        other => panic!("{:?}", other),
+    }
+}
+
+#[test]
+fn tile_position() {
+    let mut save = saved("position");
+    {
+        let tile = some_tile(&mut save);
+        let x = tile["x"].as_u64().unwrap();
+        tile["x"] = json!((x + 1) % 4);
+    }
+    match load("position", &save) {
+        Err(SaveError::TilePosition(..)) => (),
+        other => panic!("{:?}", other),
+    }
+}
+
+#[test]
+fn target_tile() {
+    for &target in [0, 2, 3, 2047].iter() {
+        let mut save = saved("target");
+        save["game"]["target_tile"] = json!(target);
+        match load("target", &save) {
+            Err(SaveError::TargetTile(t)) => assert_eq!(t as u64, target),
+            other => panic!("{}: {:?}", target, other),
+        }
+    }
+}
+
+#[test]
+fn spawn_rule() {
+    let mut save = saved("rule");
+    save["game"]["spawn_rule"]["per_move"] = json!(0);
+    match load("rule", &save) {
+        Err(SaveError::SpawnRule(_)) => (),
+        other => panic!("{:?}", other),
+    }
+}
+
+#[test]
+fn stuck_rng() {
+    let mut save = saved("rng");
+    save["game"]["rng"]["state"] = json!(0);
+    match load("rng", &save) {
+        Err(SaveError::Rng) => (),
+        other => panic!("{:?}", other),
+    }
+}
+
+// The save of `game()` as version 1 wrote it, boards being square.
+fn saved_v1(name: &str) -> Value {
+    let mut save = saved(name);
+    save["version"] = json!(1);
+    join_size(&mut save["game"]);
+    join_size(&mut save["game"]["grid"]);
+    save
+}
+
+fn join_size(value: &mut Value) {
+    let obj = value.as_object_mut().unwrap();
+    obj.remove("height");
+    let size = obj.remove("width").unwrap();
+    obj.insert("size".to_string(), size);
+}
+
+#[test]
+fn v1_upgrade() {
+    let gm = load("v1", &saved_v1("v1")).unwrap();
+    assert_eq!(gm.grid, game().grid);
+
+    let mut save = saved_v1("v1-size");
+    save["game"]["size"] = json!(9);
+    save["game"]["grid"]["size"] = json!(9);
+    match load("v1-size", &save) {
+        Err(SaveError::BoardSize(9, 9)) => (),
+        other => panic!("{:?}", other),
+    }
+
+    let mut save = saved_v1("v1-target");
+    save["game"]["target_tile"] = json!(3);
+    match load("v1-target", &save) {
+        Err(SaveError::TargetTile(3)) => (),
+        other => panic!("{:?}", other),
+    }
+}
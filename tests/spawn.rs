@@ -0,0 +1,71 @@
+extern crate game2048;
+extern crate serde_json;
+
+mod common;
+
+use game2048::{Direction, GameManager, SaveError, SpawnPolicy, SpawnRule};
+use game2048::spawn::{MAX_PER_MOVE, MAX_EVIL_DEPTH};
+
+use common::grid;
+
+fn rejected(rule: &SpawnRule) -> bool {
+    match rule.validate() {
+        Err(SaveError::SpawnRule(_)) => true,
+        _ => false,
+    }
+}
+
+#[test]
+fn per_move_is_bounded() {
+    assert!(rejected(&SpawnRule { per_move: 0, ..SpawnRule::default() }));
+    assert!(rejected(&SpawnRule { per_move: MAX_PER_MOVE + 1, ..SpawnRule::default() }));
+    assert!(SpawnRule { per_move: MAX_PER_MOVE, ..SpawnRule::default() }.validate().is_ok());
+}
+
+#[test]
+fn evil_depth_is_bounded() {
+    let evil = |depth| SpawnRule { policy: SpawnPolicy::Evil { depth: depth, chance: 1.0 }, ..SpawnRule::default() };
+    assert!(evil(MAX_EVIL_DEPTH).validate().is_ok());
+    assert!(rejected(&evil(MAX_EVIL_DEPTH + 1)));
+    assert!(rejected(&evil(50)));
+}
+
+#[test]
+fn overflowing_weights() {
+    let rule = SpawnRule { values: vec![(2, u32::max_value()), (4, 1)], ..SpawnRule::default() };
+    assert!(rejected(&rule));
+    assert!(SpawnRule { values: vec![(2, u32::max_value()), (4, 0)], ..SpawnRule::default() }.validate().is_ok());
+
+    // spawning by such a rule anyway still works
+    let mut gm = GameManager::with_seed(4, 1);
+    gm.spawn_rule = rule;
+    gm.setup();
+    gm.move_to(Direction::Up);
+    gm.move_to(Direction::Left);
+    let mut tiles = 0;
+    gm.grid.each_cell(|_, _, tile| if tile.is_some() { tiles += 1 });
+    assert!(tiles >= 2);
+}
+
+#[test]
+fn adversarial_rules_read_as_evil() {
+    let rule: SpawnRule = serde_json::from_str(r#"{"policy": {"adversarial": {"depth": 2}}}"#).unwrap();
+    assert_eq!(rule.policy, SpawnPolicy::Evil { depth: 2, chance: 1.0 });
+    let rule: SpawnRule = serde_json::from_str(r#"{"policy": {"evil": {"depth": 2}}}"#).unwrap();
+    assert_eq!(rule.policy, SpawnPolicy::Evil { depth: 2, chance: 1.0 });
+}
+
+#[test]
+fn outcomes_follow_the_weights() {
+    let board = grid(&[&[2, 4],
+                       &[0, 8]]);
+    let rule = SpawnRule { values: vec![(2, 3), (4, 0), (8, 1)], ..SpawnRule::default() };
+    let outcomes = board.spawn_outcomes(&rule).map(|(tile, p)| (tile.pos(), tile.value, p)).collect::<Vec<_>>();
+    assert_eq!(outcomes, vec![((0, 1), 2, 0.75), ((0, 1), 8, 0.25)]);
+
+    let classic = board.spawn_outcomes(&SpawnRule::default()).map(|(tile, p)| (tile.value, p)).collect::<Vec<_>>();
+    assert_eq!(classic, vec![(2, 0.9), (4, 0.1)]);
+
+    let full = grid(&[&[2, 4], &[4, 2]]);
+    assert_eq!(full.spawn_outcomes(&rule).count(), 0);
+}
@@ -0,0 +1,78 @@
+extern crate game2048;
+
+mod common;
+
+use game2048::{Direction, Symmetry};
+use game2048::Direction::{Up, Right, Down, Left};
+
+use common::{grid, merging, start};
+
+#[test]
+fn images() {
+    let images: [(Symmetry, &[&[isize]]); 8] = [
+        (Symmetry::Identity, &[&[2, 4, 0, 0], &[0, 0, 0, 0], &[0, 0, 0, 8], &[0, 0, 0, 0]]),
+        (Symmetry::Rotate90, &[&[0, 0, 0, 2], &[0, 0, 0, 4], &[0, 0, 0, 0], &[0, 8, 0, 0]]),
+        (Symmetry::Rotate180, &[&[0, 0, 0, 0], &[8, 0, 0, 0], &[0, 0, 0, 0], &[0, 0, 4, 2]]),
+        (Symmetry::Rotate270, &[&[0, 0, 8, 0], &[0, 0, 0, 0], &[4, 0, 0, 0], &[2, 0, 0, 0]]),
+        (Symmetry::FlipHorizontal, &[&[0, 0, 4, 2], &[0, 0, 0, 0], &[8, 0, 0, 0], &[0, 0, 0, 0]]),
+        (Symmetry::FlipVertical, &[&[0, 0, 0, 0], &[0, 0, 0, 8], &[0, 0, 0, 0], &[2, 4, 0, 0]]),
+        (Symmetry::Transpose, &[&[2, 0, 0, 0], &[4, 0, 0, 0], &[0, 0, 0, 0], &[0, 0, 8, 0]]),
+        (Symmetry::AntiTranspose, &[&[0, 8, 0, 0], &[0, 0, 0, 0], &[0, 0, 0, 4], &[0, 0, 0, 2]]),
+    ];
+    for &(sym, rows) in images.iter() {
+        assert_eq!(start().transform(sym), grid(rows), "{:?}", sym);
+        assert_eq!(grid(rows).transform(sym.inverse()), start(), "{:?}", sym);
+    }
+}
+
+#[test]
+fn rectangle_turns() {
+    let rect = grid(&[&[2, 0, 4],
+                      &[0, 8, 0]]);
+    assert_eq!(rect.rotate90(), grid(&[&[0, 2], &[8, 0], &[0, 4]]));
+    assert_eq!(Symmetry::preserving(3, 2),
+               vec![Symmetry::Identity, Symmetry::Rotate180, Symmetry::FlipHorizontal, Symmetry::FlipVertical]);
+}
+
+// Where Up, Right, Down and Left go.
+fn directions() -> [(Symmetry, [Direction; 4]); 8] {
+    [(Symmetry::Identity, [Up, Right, Down, Left]),
+     (Symmetry::Rotate90, [Right, Down, Left, Up]),
+     (Symmetry::Rotate180, [Down, Left, Up, Right]),
+     (Symmetry::Rotate270, [Left, Up, Right, Down]),
+     (Symmetry::FlipHorizontal, [Up, Left, Down, Right]),
+     (Symmetry::FlipVertical, [Down, Right, Up, Left]),
+     (Symmetry::Transpose, [Left, Down, Right, Up]),
+     (Symmetry::AntiTranspose, [Right, Up, Left, Down])]
+}
+
+#[test]
+fn direction_table() {
+    for &(sym, ref image) in directions().iter() {
+        let mapped = Direction::all_directions().into_iter().map(|dir| sym.direction(dir)).collect::<Vec<_>>();
+        assert_eq!(&mapped[..], &image[..], "{:?}", sym);
+    }
+}
+
+#[test]
+fn slide_commutes() {
+    for grid in [start(), merging()].iter() {
+        for &(sym, ref image) in directions().iter() {
+            for (dir, &mapped) in Direction::all_directions().into_iter().zip(image.iter()) {
+                let expected = grid.slide(dir).map(|(after, score)| (after.transform(sym), score));
+                assert_eq!(grid.transform(sym).slide(mapped), expected, "{:?} {:?}", sym, dir);
+            }
+        }
+    }
+}
+
+#[test]
+fn canonical_is_shared() {
+    for grid in [start(), merging()].iter() {
+        let (canonical, sym) = grid.canonical();
+        assert_eq!(&canonical.transform(sym.inverse()), grid);
+        for &s in Symmetry::all().iter() {
+            assert_eq!(grid.transform(s).canonical().0, canonical, "{:?}", s);
+        }
+    }
+}
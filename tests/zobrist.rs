@@ -0,0 +1,93 @@
+extern crate game2048;
+extern crate serde_json;
+
+mod common;
+
+use game2048::{Direction, GameManager, Grid, Tile};
+
+use common::{grid, merging, start};
+
+#[test]
+fn tiles_in_and_out() {
+    let mut grid = Grid::new(4);
+    assert_eq!(grid.zobrist(), 0);
+    let tile = Tile::new((1, 2), 4);
+    grid.insert_tile(tile);
+    assert!(grid.zobrist() != 0);
+    grid.remove_tile(tile);
+    assert_eq!(grid.zobrist(), 0);
+}
+
+#[test]
+fn insertion_order_does_not_count() {
+    let tiles = [Tile::new((0, 0), 2), Tile::new((1, 0), 4), Tile::new((3, 2), 8)];
+    let mut backwards = Grid::new(4);
+    for &tile in tiles.iter().rev() {
+        backwards.insert_tile(tile);
+    }
+    assert_eq!(backwards.zobrist(), start().zobrist());
+}
+
+#[test]
+fn value_and_position_count() {
+    let hash = start().zobrist();
+    let other_value = grid(&[&[2, 4, 0, 0], &[0, 0, 0, 0], &[0, 0, 0, 16], &[0, 0, 0, 0]]);
+    let other_cell = grid(&[&[2, 4, 0, 0], &[0, 0, 0, 0], &[0, 0, 8, 0], &[0, 0, 0, 0]]);
+    assert!(other_value.zobrist() != hash);
+    assert!(other_cell.zobrist() != hash);
+}
+
+#[test]
+fn slides_keep_it_up() {
+    let (after, _) = merging().slide(Direction::Left).unwrap();
+    let expected = grid(&[&[4, 4, 0, 0],
+                          &[4, 0, 0, 0],
+                          &[2, 0, 0, 0],
+                          &[16, 0, 0, 0]]);
+    assert_eq!(after.zobrist(), expected.zobrist());
+
+    let (canonical, sym) = after.canonical();
+    assert_eq!(canonical.transform(sym.inverse()).zobrist(), after.zobrist());
+}
+
+#[test]
+fn undo_and_redo() {
+    let mut gm = GameManager::with_seed(4, 9);
+    gm.grid = start();
+    let before = gm.grid.zobrist();
+    assert!(gm.move_to(Direction::Left).moved());
+    let after = gm.grid.zobrist();
+    assert!(after != before);
+
+    assert!(gm.undo());
+    assert_eq!(gm.grid.zobrist(), before);
+    assert!(gm.redo());
+    assert_eq!(gm.grid.zobrist(), after);
+
+    let mut fresh = gm.grid.clone();
+    fresh.rehash();
+    assert_eq!(fresh.zobrist(), after);
+}
+
+#[test]
+fn serde_round_trip() {
+    let mut gm = GameManager::with_seed(4, 11);
+    gm.setup();
+    for &dir in [Direction::Left, Direction::Up, Direction::Right].iter() {
+        gm.move_to(dir);
+    }
+    let json = serde_json::to_string(&gm.grid).unwrap();
+    let grid: Grid = serde_json::from_str(&json).unwrap();
+    assert!(gm.grid.zobrist() != 0);
+    assert_eq!(grid.zobrist(), gm.grid.zobrist());
+    assert_eq!(grid, gm.grid);
+
+    // equality goes by the cells, even when a hash is stale
+    let tile = Tile::new(gm.grid.available_cells()[0], 2);
+    let mut inserted = gm.grid.clone();
+    inserted.insert_tile(tile);
+    let mut written = gm.grid.clone();
+    written.cells[tile.x][tile.y] = Some(tile);
+    assert!(written.zobrist() != inserted.zobrist());
+    assert_eq!(written, inserted);
+}